@@ -0,0 +1,59 @@
+/*
+cargo bench -p ip-api-api --bench de_response_body_json
+*/
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use ip_api_api::endpoints::{
+    batch::BatchResponseBodyJson, borrowed::BatchResponseBodyJsonRef, json::JsonResponseBodyJson,
+};
+use serde::Deserialize as _;
+use serde_json::{Map, Value};
+
+const JSON_FULL_FIELDS: &str =
+    include_str!("../tests/response_body_json_files/json_full_fields.json");
+const BATCH_SIMPLE: &str = include_str!("../tests/response_body_json_files/batch_simple.json");
+const BATCH_SIMPLE_WITH_PART_ERR: &str =
+    include_str!("../tests/response_body_json_files/batch_simple_with_part_err.json");
+
+fn batch_100() -> String {
+    let element = JSON_FULL_FIELDS.trim();
+    format!("[{}]", vec![element; 100].join(","))
+}
+
+/// The previous approach, materializing a `Map` and re-deserializing via `Value::Object`.
+fn de_via_map(s: &str) -> Vec<JsonResponseBodyJson> {
+    serde_json::from_str::<Vec<Map<String, Value>>>(s)
+        .unwrap()
+        .into_iter()
+        .map(|map| JsonResponseBodyJson::deserialize(Value::Object(map)).unwrap())
+        .collect()
+}
+
+fn de_single_pass(s: &str) -> Vec<JsonResponseBodyJson> {
+    serde_json::from_str::<BatchResponseBodyJson>(s).unwrap().0
+}
+
+fn de_borrowed(s: &str) -> usize {
+    serde_json::from_str::<BatchResponseBodyJsonRef>(s)
+        .unwrap()
+        .len()
+}
+
+fn bench(c: &mut Criterion) {
+    let batch_100 = batch_100();
+
+    for (name, s) in [
+        ("batch_simple", BATCH_SIMPLE),
+        ("batch_simple_with_part_err", BATCH_SIMPLE_WITH_PART_ERR),
+        ("batch_100_full_fields", batch_100.as_str()),
+    ] {
+        let mut group = c.benchmark_group(name);
+        group.bench_function("via_map", |b| b.iter(|| de_via_map(s)));
+        group.bench_function("single_pass", |b| b.iter(|| de_single_pass(s)));
+        group.bench_function("borrowed", |b| b.iter(|| de_borrowed(s)));
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
@@ -0,0 +1,195 @@
+//! nginx and Apache access logs in the common or combined log format.
+//!
+//! `%h %l %u %t "%r" %>s %b "%{Referer}i" "%{User-agent}i"`, which is also nginx's `combined`.
+//! Only the client, `%h` or `$remote_addr`, is required to parse, i.e. `HostnameLookups Off`.
+
+use std::{collections::HashSet, net::IpAddr};
+
+use crate::endpoints::json::JsonResponseBodyJson;
+
+/// Written for unknown annotations, like `%b` does for no bytes.
+pub const EMPTY_FIELD: &str = "-";
+
+//
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry<'a> {
+    pub remote_addr: IpAddr,
+    pub ident: &'a str,
+    pub user: &'a str,
+    /// Without the brackets, e.g. "10/Oct/2000:13:55:36 -0700".
+    pub time: &'a str,
+    /// Without the quotes, e.g. "GET /apache_pb.gif HTTP/1.0".
+    pub request: &'a str,
+    pub status: Option<u16>,
+    pub bytes: Option<u64>,
+    /// Combined format only.
+    pub referer: Option<&'a str>,
+    pub user_agent: Option<&'a str>,
+}
+
+impl<'a> LogEntry<'a> {
+    /// `None` when the line is not in the common or combined format.
+    pub fn parse(line: &'a str) -> Option<Self> {
+        let mut fields = Fields(line);
+        let remote_addr = fields.next()?.parse().ok()?;
+        let ident = fields.next()?;
+        let user = fields.next()?;
+        let time = fields.next_delimited('[', ']')?;
+        let request = fields.next_delimited('"', '"')?;
+        let status = fields.next()?.parse().ok();
+        let bytes = fields.next()?.parse().ok();
+        let referer = fields.next_delimited('"', '"');
+        let user_agent = fields.next_delimited('"', '"');
+
+        Some(Self {
+            remote_addr,
+            ident,
+            user,
+            time,
+            request,
+            status,
+            bytes,
+            referer,
+            user_agent,
+        })
+    }
+}
+
+struct Fields<'a>(&'a str);
+
+impl<'a> Fields<'a> {
+    fn next(&mut self) -> Option<&'a str> {
+        let s = self.0.trim_start();
+        if s.is_empty() {
+            return None;
+        }
+        let end = s.find(char::is_whitespace).unwrap_or(s.len());
+        self.0 = &s[end..];
+        Some(&s[..end])
+    }
+
+    /// Backslash escapes the closing delimiter, as in `\"` within `%r`.
+    fn next_delimited(&mut self, open: char, close: char) -> Option<&'a str> {
+        let s = self.0.trim_start().strip_prefix(open)?;
+        let mut escaped = false;
+        for (i, c) in s.char_indices() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                c if c == close => {
+                    self.0 = &s[i + c.len_utf8()..];
+                    return Some(&s[..i]);
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+/// The client of any line starting with an IP address, even if the rest is not parseable.
+pub fn remote_addr(line: &str) -> Option<IpAddr> {
+    Fields(line).next()?.parse().ok()
+}
+
+/// Clients in order of first appearance.
+pub fn remote_addrs<'a>(lines: impl IntoIterator<Item = &'a str>) -> Vec<IpAddr> {
+    let mut ips: Vec<IpAddr> = vec![];
+    let mut seen = HashSet::new();
+    for ip in lines.into_iter().filter_map(remote_addr) {
+        if seen.insert(ip) {
+            ips.push(ip);
+        }
+    }
+    ips
+}
+
+/// `line` followed by the quoted country code and ASN, e.g. `.. "CA" "AS5769"`, `-` when unknown
+/// or not in `fields`.
+pub fn annotate(line: &str, result: Option<&JsonResponseBodyJson>) -> String {
+    let success = result.and_then(JsonResponseBodyJson::as_success);
+    let country_code = success
+        .filter(|x| x.present.contains("countryCode"))
+        .map(|x| x.country_code.to_string());
+    let asn = success
+        .filter(|x| x.present.contains("as"))
+        .and_then(|x| x.asn())
+        .map(|x| x.to_string());
+    format!(
+        r#"{} "{}" "{}""#,
+        line,
+        country_code.as_deref().unwrap_or(EMPTY_FIELD),
+        asn.as_deref().unwrap_or(EMPTY_FIELD)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMBINED: &str = r#"24.48.0.1 - frank [10/Oct/2000:13:55:36 -0700] "GET /a\"b HTTP/1.0" 200 2326 "http://www.example.com/start.html" "Mozilla/4.08 [en] (Win98; I ;Nav)""#;
+
+    #[test]
+    fn test_parse() {
+        let entry = LogEntry::parse(COMBINED).unwrap();
+        assert_eq!(entry.remote_addr, "24.48.0.1".parse::<IpAddr>().unwrap());
+        assert_eq!(entry.user, "frank");
+        assert_eq!(entry.time, "10/Oct/2000:13:55:36 -0700");
+        assert_eq!(entry.request, r#"GET /a\"b HTTP/1.0"#);
+        assert_eq!(entry.status, Some(200));
+        assert_eq!(entry.bytes, Some(2326));
+        assert_eq!(entry.referer, Some("http://www.example.com/start.html"));
+        assert_eq!(entry.user_agent, Some("Mozilla/4.08 [en] (Win98; I ;Nav)"));
+
+        let entry =
+            LogEntry::parse(r#"2001:db8::1 - - [10/Oct/2000:13:55:36 -0700] "-" 400 -"#).unwrap();
+        assert_eq!(entry.remote_addr, "2001:db8::1".parse::<IpAddr>().unwrap());
+        assert_eq!(entry.request, "-");
+        assert_eq!(entry.bytes, None);
+        assert_eq!(entry.referer, None);
+
+        assert!(
+            LogEntry::parse("example.com - - [10/Oct/2000:13:55:36 -0700] \"-\" 400 -").is_none()
+        );
+        assert!(LogEntry::parse("24.48.0.1 - - [10/Oct/2000:13:55:36 -0700] \"GET /").is_none());
+        assert!(LogEntry::parse("").is_none());
+    }
+
+    #[test]
+    fn test_remote_addrs() {
+        assert_eq!(
+            remote_addrs([COMBINED, "", "garbage", "10.0.0.1 whatever", COMBINED]),
+            [
+                "24.48.0.1".parse::<IpAddr>().unwrap(),
+                "10.0.0.1".parse().unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_annotate() {
+        let line = r#"24.48.0.1 - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.0" 200 -"#;
+        let success: JsonResponseBodyJson = serde_json::from_str(
+            r#"{"status":"success","countryCode":"CA","as":"AS5769 Videotron Telecom Ltee","query":"24.48.0.1"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            annotate(line, Some(&success)),
+            format!(r#"{} "CA" "AS5769""#, line)
+        );
+        assert_eq!(annotate(line, None), format!(r#"{} "-" "-""#, line));
+        let without_fields: JsonResponseBodyJson =
+            serde_json::from_str(r#"{"status":"success","country":"Canada","query":"24.48.0.1"}"#)
+                .unwrap();
+        assert_eq!(
+            annotate(line, Some(&without_fields)),
+            format!(r#"{} "-" "-""#, line)
+        );
+
+        // Still parseable as the combined format.
+        let annotated = annotate(line, Some(&success));
+        let entry = LogEntry::parse(&annotated).unwrap();
+        assert_eq!(entry.referer, Some("CA"));
+        assert_eq!(entry.user_agent, Some("AS5769"));
+    }
+}
@@ -0,0 +1,294 @@
+//! Look up any number of addresses with `Batch` requests of up to `MAX_QUERY` each.
+//!
+//! Cached addresses are skipped and every chunk is cached as soon as it completes, so an
+//! interrupted run resumes where it stopped. Without a key, the next chunk waits for `X-Ttl`
+//! whenever `X-Rl` reaches 0.
+//!
+//! `lookup_prefixes`, with the `prefix` feature, does the same for addresses sampled from
+//! prefixes, and summarizes them.
+
+use core::{fmt, future::Future, time::Duration};
+use std::{io, net::IpAddr};
+
+use http_api_client::Client;
+
+#[cfg(feature = "prefix")]
+use crate::prefix::{Prefix, PrefixSummary};
+use crate::{
+    client::fallback::RespondError,
+    endpoints::batch::{Batch, BatchQuery, MAX_QUERY},
+    lookup_cache::LookupCache,
+    objects::rate_limit::RateLimit,
+};
+
+/// When `X-Ttl` is missing, the free rate limit window is a minute.
+pub const RATE_LIMIT_WAIT_DEFAULT: Duration = Duration::from_secs(60);
+
+/// Lookups requested, cached results are not counted.
+///
+/// `template` provides the key, `fields` and `lang`, its queries are ignored. `sleep` is the
+/// runtime's, e.g. `tokio::time::sleep`.
+pub async fn lookup_all<C, S, F>(
+    client: &C,
+    template: &Batch,
+    ips: &[IpAddr],
+    cache: &mut LookupCache,
+    sleep: S,
+) -> Result<usize, BulkError<C::RespondError>>
+where
+    C: Client + Send + Sync,
+    S: Fn(Duration) -> F,
+    F: Future<Output = ()>,
+{
+    let missing = cache.missing(ips);
+
+    let mut chunks = missing.chunks(MAX_QUERY).peekable();
+    while let Some(chunk) = chunks.next() {
+        let batch = Batch {
+            queries: chunk
+                .iter()
+                .map(|x| BatchQuery::new(x.to_string()))
+                .collect(),
+            ..template.clone()
+        };
+        let (results, rate_limit) = client
+            .respond_endpoint(&batch)
+            .await
+            .map_err(BulkError::Respond)?;
+
+        for (ip, result) in chunk.iter().zip(results.0) {
+            cache.insert(*ip, result).map_err(BulkError::Cache)?;
+        }
+
+        if chunks.peek().is_some()
+            && let Some(dur) = rate_limit.as_ref().and_then(rate_limit_wait)
+        {
+            sleep(dur).await;
+        }
+    }
+
+    Ok(missing.len())
+}
+
+/// Summaries in the order of `samples`, e.g. from `Prefix::sample`.
+#[cfg(feature = "prefix")]
+pub async fn lookup_prefixes<C, S, F>(
+    client: &C,
+    template: &Batch,
+    samples: &[(Prefix, Vec<IpAddr>)],
+    cache: &mut LookupCache,
+    sleep: S,
+) -> Result<Vec<PrefixSummary>, BulkError<C::RespondError>>
+where
+    C: Client + Send + Sync,
+    S: Fn(Duration) -> F,
+    F: Future<Output = ()>,
+{
+    let ips = samples
+        .iter()
+        .flat_map(|(_, ips)| ips.iter().copied())
+        .collect::<Vec<_>>();
+    lookup_all(client, template, &ips, cache, sleep).await?;
+
+    Ok(samples
+        .iter()
+        .map(|(prefix, ips)| PrefixSummary::new(*prefix, ips.iter().map(|ip| cache.get(ip))))
+        .collect())
+}
+
+/// Before the next request, `None` unless `X-Rl` reached 0.
+pub fn rate_limit_wait(rate_limit: &RateLimit) -> Option<Duration> {
+    match rate_limit.remaining {
+        Some(0) => Some(
+            rate_limit
+                .seconds_until_reset
+                .map(|x| Duration::from_secs(x as u64 + 1))
+                .unwrap_or(RATE_LIMIT_WAIT_DEFAULT),
+        ),
+        _ => None,
+    }
+}
+
+//
+#[derive(Debug)]
+pub enum BulkError<RE>
+where
+    RE: core::error::Error + Send + Sync + 'static,
+{
+    Respond(RespondError<RE>),
+    Cache(io::Error),
+}
+
+impl<RE> fmt::Display for BulkError<RE>
+where
+    RE: core::error::Error + Send + Sync + 'static,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl<RE> std::error::Error for BulkError<RE> where RE: core::error::Error + Send + Sync + 'static {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{io::Error as IoError, sync::Mutex};
+
+    use http_api_client::{Body, Request, Response, async_trait};
+    use serde_json::Value;
+
+    use crate::objects::rate_limit::{RESPONSE_HEADER_KEY_X_RL, RESPONSE_HEADER_KEY_X_TTL};
+
+    /// Echoes every query as a success, with `X-Rl: 0` on every other request.
+    #[derive(Debug, Default)]
+    struct MockClient {
+        batch_sizes: Mutex<Vec<usize>>,
+    }
+
+    #[async_trait]
+    impl Client for MockClient {
+        type RespondError = IoError;
+
+        async fn respond(
+            &self,
+            request: Request<Body>,
+        ) -> Result<Response<Body>, Self::RespondError> {
+            let queries: Vec<String> = serde_json::from_slice(request.body()).unwrap();
+            let results = queries
+                .iter()
+                .map(|x| serde_json::json!({"status": "success", "countryCode": "CA", "query": x}))
+                .collect::<Value>();
+
+            let mut batch_sizes = self.batch_sizes.lock().unwrap();
+            batch_sizes.push(queries.len());
+            let remaining = if batch_sizes.len() % 2 == 1 { 0 } else { 14 };
+            Ok(Response::builder()
+                .header(RESPONSE_HEADER_KEY_X_RL, remaining)
+                .header(RESPONSE_HEADER_KEY_X_TTL, 30)
+                .body(serde_json::to_vec(&results).unwrap())
+                .unwrap())
+        }
+    }
+
+    fn ips(n: usize) -> Vec<IpAddr> {
+        (0..n)
+            .map(|i| IpAddr::from([24, 48, (i / 256) as u8, (i % 256) as u8]))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_lookup_all() {
+        let client = MockClient::default();
+        let template = Batch::new(vec![], None).fields("status,countryCode,query");
+        let sleeps = Mutex::new(vec![]);
+        let sleep = |dur| {
+            sleeps.lock().unwrap().push(dur);
+            async {}
+        };
+
+        let mut cache = LookupCache::new();
+        cache
+            .insert(
+                ips(1)[0],
+                serde_json::from_str(
+                    r#"{"status":"fail","message":"reserved range","query":"24.48.0.0"}"#,
+                )
+                .unwrap(),
+            )
+            .unwrap();
+
+        let mut ips = ips(250);
+        ips.extend(ips.clone());
+        assert_eq!(
+            lookup_all(&client, &template, &ips, &mut cache, sleep)
+                .await
+                .unwrap(),
+            249
+        );
+        assert_eq!(*client.batch_sizes.lock().unwrap(), [100, 100, 49]);
+        // After the first and the third, but nothing left after the third.
+        assert_eq!(*sleeps.lock().unwrap(), [Duration::from_secs(31)]);
+        assert_eq!(cache.len(), 250);
+        assert!(
+            cache
+                .get(&ips[249])
+                .is_some_and(|x| x.as_success().is_some_and(|x| x.query == ips[249]))
+        );
+        assert!(!cache.get(&ips[0]).unwrap().is_success());
+
+        // Resumed, nothing left to look up.
+        assert_eq!(
+            lookup_all(&client, &template, &ips, &mut cache, sleep)
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(client.batch_sizes.lock().unwrap().len(), 3);
+    }
+
+    #[cfg(feature = "prefix")]
+    #[tokio::test]
+    async fn test_lookup_prefixes() {
+        use country_code::CountryCode;
+        use rand::SeedableRng as _;
+
+        use crate::prefix::Sampling;
+
+        let client = MockClient::default();
+        let template = Batch::new(vec![], None).fields("status,countryCode,as,query");
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+
+        let samples = ["24.48.0.0/22", "24.48.0.0/24", "24.48.8.0-24.48.8.9"]
+            .into_iter()
+            .map(|x| {
+                let prefix: Prefix = x.parse().unwrap();
+                let ips = prefix.sample(Sampling::EVERY_24, &mut rng).unwrap();
+                (prefix, ips)
+            })
+            .collect::<Vec<_>>();
+        let summaries = lookup_prefixes(
+            &client,
+            &template,
+            &samples,
+            &mut LookupCache::new(),
+            |_| async {},
+        )
+        .await
+        .unwrap();
+
+        // 24.48.0.1 is shared by the first two.
+        assert_eq!(*client.batch_sizes.lock().unwrap(), [5]);
+        assert_eq!(
+            summaries
+                .iter()
+                .map(|x| (x.prefix.to_string(), x.sampled, x.failed))
+                .collect::<Vec<_>>(),
+            [
+                ("24.48.0.0/22".to_owned(), 4, 0),
+                ("24.48.0.0/24".to_owned(), 1, 0),
+                ("24.48.8.0-24.48.8.9".to_owned(), 1, 0),
+            ]
+        );
+        assert_eq!(summaries[0].countries, [(CountryCode::CA, 4)]);
+    }
+
+    #[test]
+    fn test_rate_limit_wait() {
+        let rate_limit = |remaining, seconds_until_reset| RateLimit {
+            remaining,
+            seconds_until_reset,
+        };
+        assert_eq!(rate_limit_wait(&rate_limit(Some(14), Some(30))), None);
+        assert_eq!(rate_limit_wait(&rate_limit(None, None)), None);
+        assert_eq!(
+            rate_limit_wait(&rate_limit(Some(0), Some(0))),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(
+            rate_limit_wait(&rate_limit(Some(0), None)),
+            Some(RATE_LIMIT_WAIT_DEFAULT)
+        );
+    }
+}
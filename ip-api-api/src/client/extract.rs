@@ -0,0 +1,268 @@
+//! axum extractors geolocating the requesting client.
+//!
+//! The client IP is resolved from the `ConnectInfo` peer and headers by `ClientIpResolver`.
+
+use core::{fmt, time::Duration};
+use std::net::{IpAddr, SocketAddr};
+
+use axum::{
+    extract::{ConnectInfo, FromRef, FromRequestParts},
+    http::{HeaderMap, StatusCode, request::Parts},
+    response::{IntoResponse, Response},
+};
+use http_api_client::Client;
+use ipnet::IpNet;
+use tower::{BoxError, Layer as _, Service, ServiceExt as _, util::BoxCloneSyncService};
+
+use crate::{
+    client::service::{CacheLayer, LookupService},
+    client_ip::{ClientIpResolver, ForwardedHeader},
+    endpoints::json::{
+        JsonResponseBodyFailJson, JsonResponseBodyJson, JsonResponseBodySuccessJson,
+    },
+    types::api_key::ApiKey,
+};
+
+pub const CACHE_TTL_DEFAULT: Duration = Duration::from_secs(60 * 60);
+pub const CACHE_CAPACITY_DEFAULT: usize = 10_000;
+
+//
+#[derive(Clone)]
+pub struct GeoLocator {
+    service: BoxCloneSyncService<IpAddr, JsonResponseBodyJson, BoxError>,
+    resolver: ClientIpResolver,
+}
+
+impl fmt::Debug for GeoLocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GeoLocator")
+            .field("resolver", &self.resolver)
+            .finish_non_exhaustive()
+    }
+}
+
+impl GeoLocator {
+    /// Any lookup service, e.g. a `LookupService` stacked with your own layers.
+    pub fn new<S>(service: S) -> Self
+    where
+        S: Service<IpAddr, Response = JsonResponseBodyJson> + Clone + Send + Sync + 'static,
+        S::Error: Into<BoxError>,
+        S::Future: Send + 'static,
+    {
+        Self {
+            service: BoxCloneSyncService::new(service.map_err(Into::into)),
+            resolver: ClientIpResolver::new(),
+        }
+    }
+
+    /// `LookupService` behind a `CacheLayer` with the default ttl and capacity, without the rate limit.
+    /// Must be called on a Tokio runtime.
+    pub fn cached<C>(client: C, key: Option<ApiKey>) -> Self
+    where
+        C: Client + Send + Sync + 'static,
+    {
+        Self::new(
+            CacheLayer::new(CACHE_TTL_DEFAULT, CACHE_CAPACITY_DEFAULT)
+                .layer(LookupService::new(client, key))
+                .map_response(|(json, _)| json),
+        )
+    }
+
+    /// Only these peers may set the forwarding headers, empty by default.
+    pub fn trusted_proxies(mut self, proxies: impl IntoIterator<Item = IpNet>) -> Self {
+        self.resolver = self.resolver.trusted_proxies(proxies);
+        self
+    }
+
+    /// The header those proxies set, `X-Forwarded-For` by default.
+    pub fn forwarded_header(mut self, header: ForwardedHeader) -> Self {
+        self.resolver = self.resolver.header(header);
+        self
+    }
+
+    pub fn resolver(mut self, resolver: ClientIpResolver) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
+    pub fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        self.resolver.resolve(peer, headers)
+    }
+
+    pub async fn lookup(&self, ip: IpAddr) -> Result<JsonResponseBodyJson, BoxError> {
+        self.service.clone().oneshot(ip).await
+    }
+}
+
+//
+//
+//
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+impl<S> FromRequestParts<S> for ClientIp
+where
+    S: Send + Sync,
+    GeoLocator: FromRef<S>,
+{
+    type Rejection = ClientGeoRejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let ConnectInfo(peer) = ConnectInfo::<SocketAddr>::from_request_parts(parts, state)
+            .await
+            .map_err(|_| ClientGeoRejection::MissingConnectInfo)?;
+        let locator = GeoLocator::from_ref(state);
+        Ok(Self(locator.client_ip(peer.ip(), &parts.headers)))
+    }
+}
+
+/// Use `Result<ClientGeo, ClientGeoRejection>` to handle fails yourself.
+#[derive(Debug, Clone)]
+pub struct ClientGeo(pub Box<JsonResponseBodySuccessJson>);
+
+impl<S> FromRequestParts<S> for ClientGeo
+where
+    S: Send + Sync,
+    GeoLocator: FromRef<S>,
+{
+    type Rejection = ClientGeoRejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let ClientIp(ip) = ClientIp::from_request_parts(parts, state).await?;
+        let locator = GeoLocator::from_ref(state);
+        match locator
+            .lookup(ip)
+            .await
+            .map_err(ClientGeoRejection::LookupFailed)?
+        {
+            JsonResponseBodyJson::Success(x) => Ok(Self(x)),
+            JsonResponseBodyJson::Fail(x) => Err(ClientGeoRejection::Fail(x)),
+        }
+    }
+}
+
+//
+#[derive(Debug)]
+pub enum ClientGeoRejection {
+    /// The server was not started with `into_make_service_with_connect_info::<SocketAddr>()`.
+    MissingConnectInfo,
+    LookupFailed(BoxError),
+    /// e.g. a private range address.
+    Fail(JsonResponseBodyFailJson),
+}
+
+impl fmt::Display for ClientGeoRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for ClientGeoRejection {}
+
+impl IntoResponse for ClientGeoRejection {
+    fn into_response(self) -> Response {
+        match self {
+            Self::MissingConnectInfo => {
+                (StatusCode::INTERNAL_SERVER_ERROR, "missing client address").into_response()
+            }
+            Self::LookupFailed(_) => {
+                (StatusCode::BAD_GATEWAY, "geolocation failed").into_response()
+            }
+            Self::Fail(x) => {
+                (StatusCode::UNPROCESSABLE_ENTITY, x.message.to_string()).into_response()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::client_ip::HEADER_X_FORWARDED_FOR;
+
+    use axum::{
+        Router,
+        body::{Body, to_bytes},
+        extract::connect_info::MockConnectInfo,
+        http::Request,
+        routing::get,
+    };
+
+    const SUCCESS: &str = include_str!("../../tests/response_body_json_files/json_default.json");
+
+    fn locator() -> GeoLocator {
+        GeoLocator::new(tower::service_fn(|ip: IpAddr| async move {
+            let json = if ip.to_string() == "24.48.0.1" {
+                SUCCESS.to_owned()
+            } else {
+                format!(
+                    r#"{{"status":"fail","message":"private range","query":"{}"}}"#,
+                    ip
+                )
+            };
+            serde_json::from_str::<JsonResponseBodyJson>(&json)
+        }))
+        .trusted_proxies(["10.0.0.0/8".parse().unwrap()])
+    }
+
+    #[test]
+    fn test_client_ip() {
+        let locator = locator();
+        let mut headers = HeaderMap::new();
+        headers.insert(HEADER_X_FORWARDED_FOR, "24.48.0.1".parse().unwrap());
+        assert_eq!(
+            locator.client_ip("10.0.0.1".parse().unwrap(), &headers),
+            "24.48.0.1".parse::<IpAddr>().unwrap()
+        );
+        assert_eq!(
+            locator.client_ip("1.1.1.1".parse().unwrap(), &headers),
+            "1.1.1.1".parse::<IpAddr>().unwrap()
+        );
+    }
+
+    async fn call(router: Router, peer: &str, xff: Option<&str>) -> (StatusCode, String) {
+        let router = router.layer(MockConnectInfo(SocketAddr::new(peer.parse().unwrap(), 80)));
+        let mut request = Request::builder().uri("/");
+        if let Some(xff) = xff {
+            request = request.header(HEADER_X_FORWARDED_FOR, xff);
+        }
+        let response = router
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), 1024).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_extractor() {
+        let router = Router::new()
+            .route(
+                "/",
+                get(|ClientGeo(json): ClientGeo| async move { json.country_code.to_string() }),
+            )
+            .with_state(locator());
+
+        assert_eq!(
+            call(router.clone(), "10.0.0.1", Some("24.48.0.1")).await,
+            (StatusCode::OK, "CA".to_owned())
+        );
+        assert_eq!(
+            call(router, "10.0.0.1", None).await,
+            (StatusCode::UNPROCESSABLE_ENTITY, "private range".to_owned())
+        );
+
+        let router = Router::new()
+            .route(
+                "/",
+                get(|ClientIp(ip): ClientIp| async move { ip.to_string() }),
+            )
+            .with_state(locator());
+        assert_eq!(
+            call(router, "1.1.1.1", Some("24.48.0.1")).await,
+            (StatusCode::OK, "1.1.1.1".to_owned())
+        );
+    }
+}
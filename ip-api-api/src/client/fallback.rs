@@ -0,0 +1,393 @@
+//! Fall back from `URL_BASE_PRO` to the free `URL_BASE` when the pro key is invalid or expired.
+
+use core::{fmt, time::Duration};
+use std::{
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use http_api_client::{Client, ClientRespondEndpointError};
+use http_api_client_endpoint::Endpoint;
+
+use crate::{
+    client::KeyedEndpoint,
+    endpoints::{common::EndpointError, key_probe::KeyProbe},
+    objects::rate_limit::RateLimit,
+    types::api_key::ApiKey,
+};
+
+pub const PROBE_INTERVAL_DEFAULT: Duration = Duration::from_secs(60);
+
+pub type RespondError<RE> = ClientRespondEndpointError<RE, EndpointError, EndpointError>;
+
+//
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FallbackMode {
+    Pro,
+    /// Degraded, queries go to the free endpoint.
+    Free,
+}
+
+type ModeChangeCallback = Arc<dyn Fn(FallbackMode) + Send + Sync>;
+
+//
+pub struct FallbackClient<C> {
+    client: C,
+    key: ApiKey,
+    probe_interval: Duration,
+    on_mode_change: Option<ModeChangeCallback>,
+    state: Mutex<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    mode: FallbackMode,
+    last_probe_at: Option<Instant>,
+    rate_limit: Option<(RateLimit, Instant)>,
+    free_requests: u64,
+}
+
+impl<C> fmt::Debug for FallbackClient<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FallbackClient")
+            .field("key", &self.key)
+            .field("probe_interval", &self.probe_interval)
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<C> FallbackClient<C> {
+    pub fn new(client: C, key: ApiKey) -> Self {
+        Self {
+            client,
+            key,
+            probe_interval: PROBE_INTERVAL_DEFAULT,
+            on_mode_change: None,
+            state: Mutex::new(State {
+                mode: FallbackMode::Pro,
+                last_probe_at: None,
+                rate_limit: None,
+                free_requests: 0,
+            }),
+        }
+    }
+
+    /// Minimum time between two key probes while in `FallbackMode::Free`.
+    pub fn probe_interval(mut self, dur: Duration) -> Self {
+        self.probe_interval = dur;
+        self
+    }
+
+    pub fn on_mode_change(mut self, f: impl Fn(FallbackMode) + Send + Sync + 'static) -> Self {
+        self.on_mode_change = Some(Arc::new(f));
+        self
+    }
+
+    pub fn mode(&self) -> FallbackMode {
+        self.state.lock().expect("Never").mode
+    }
+
+    /// Number of requests sent to the free endpoint so far.
+    pub fn free_requests(&self) -> u64 {
+        self.state.lock().expect("Never").free_requests
+    }
+
+    fn set_mode(&self, mode: FallbackMode) {
+        let changed = {
+            let mut state = self.state.lock().expect("Never");
+            let changed = state.mode != mode;
+            state.mode = mode;
+            if mode == FallbackMode::Free {
+                state.last_probe_at = Some(Instant::now());
+            }
+            changed
+        };
+        if changed && let Some(f) = &self.on_mode_change {
+            f(mode)
+        }
+    }
+}
+
+impl<C> FallbackClient<C>
+where
+    C: Client + Send + Sync,
+{
+    pub async fn respond_endpoint<EP, T>(
+        &self,
+        endpoint: &EP,
+    ) -> Result<(T, Option<RateLimit>), FallbackError<C::RespondError>>
+    where
+        EP: Endpoint<
+                RenderRequestError = EndpointError,
+                ParseResponseOutput = (T, Option<RateLimit>),
+                ParseResponseError = EndpointError,
+            > + KeyedEndpoint
+            + Send
+            + Sync,
+    {
+        if self.mode() == FallbackMode::Free && self.probe_due() {
+            // Not conclusive, stay on the free endpoint until the next probe.
+            let _ = self.probe().await;
+        }
+
+        if self.mode() == FallbackMode::Pro {
+            let endpoint = endpoint.with_key(Some(self.key.clone()));
+            match self.client.respond_endpoint(&endpoint).await {
+                Err(ClientRespondEndpointError::EndpointParseResponseFailed(
+                    EndpointError::KeyInvalid(_),
+                )) => self.set_mode(FallbackMode::Free),
+                ret => return ret.map_err(FallbackError::Respond),
+            }
+        }
+
+        self.respond_free(endpoint).await
+    }
+
+    /// Check the key now, switching back to `FallbackMode::Pro` when it is accepted and to
+    /// `FallbackMode::Free` when it is rejected. Other errors, e.g. network ones, keep the mode.
+    pub async fn probe(&self) -> Result<FallbackMode, RespondError<C::RespondError>> {
+        let probe = KeyProbe::new(self.key.clone());
+        match self.client.respond_endpoint(&probe).await {
+            Ok(()) => self.set_mode(FallbackMode::Pro),
+            Err(ClientRespondEndpointError::EndpointParseResponseFailed(
+                EndpointError::KeyInvalid(_),
+            )) => self.set_mode(FallbackMode::Free),
+            Err(err) => {
+                self.state.lock().expect("Never").last_probe_at = Some(Instant::now());
+                return Err(err);
+            }
+        }
+        Ok(self.mode())
+    }
+
+    fn probe_due(&self) -> bool {
+        let state = self.state.lock().expect("Never");
+        state
+            .last_probe_at
+            .is_none_or(|x| x.elapsed() >= self.probe_interval)
+    }
+
+    async fn respond_free<EP, T>(
+        &self,
+        endpoint: &EP,
+    ) -> Result<(T, Option<RateLimit>), FallbackError<C::RespondError>>
+    where
+        EP: Endpoint<
+                RenderRequestError = EndpointError,
+                ParseResponseOutput = (T, Option<RateLimit>),
+                ParseResponseError = EndpointError,
+            > + KeyedEndpoint
+            + Send
+            + Sync,
+    {
+        {
+            let mut state = self.state.lock().expect("Never");
+            if let Some((rate_limit, at)) = &state.rate_limit
+                && rate_limit.remaining == Some(0)
+            {
+                let reset_in =
+                    Duration::from_secs(rate_limit.seconds_until_reset.unwrap_or_default() as u64);
+                if let Some(retry_in) = reset_in.checked_sub(at.elapsed())
+                    && !retry_in.is_zero()
+                {
+                    return Err(FallbackError::RateLimited { retry_in });
+                }
+            }
+            state.free_requests += 1;
+        }
+
+        let endpoint = endpoint.with_key(None);
+        let (body, rate_limit) = self
+            .client
+            .respond_endpoint(&endpoint)
+            .await
+            .map_err(FallbackError::Respond)?;
+
+        if let Some(rate_limit) = rate_limit {
+            self.state.lock().expect("Never").rate_limit = Some((rate_limit, Instant::now()));
+        }
+
+        Ok((body, rate_limit))
+    }
+}
+
+//
+#[derive(Debug)]
+pub enum FallbackError<RE>
+where
+    RE: core::error::Error + Send + Sync + 'static,
+{
+    Respond(RespondError<RE>),
+    /// The free endpoint rate limit is exhausted.
+    RateLimited {
+        retry_in: Duration,
+    },
+}
+
+impl<RE> fmt::Display for FallbackError<RE>
+where
+    RE: core::error::Error + Send + Sync + 'static,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl<RE> std::error::Error for FallbackError<RE> where RE: core::error::Error + Send + Sync + 'static
+{}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{
+        collections::VecDeque,
+        io::{Error as IoError, ErrorKind},
+    };
+
+    use futures_executor::block_on;
+    use http_api_client::{Body, Request, Response, async_trait};
+
+    use crate::endpoints::json::{Json, JsonResponseBodyJson};
+
+    #[derive(Default)]
+    struct MockClient {
+        responses: Mutex<VecDeque<Result<Response<Body>, IoError>>>,
+        uris: Mutex<Vec<String>>,
+    }
+
+    impl MockClient {
+        fn push(&self, status: u16, headers: &[(&str, &str)], body: &[u8]) {
+            let mut builder = Response::builder().status(status);
+            for (k, v) in headers {
+                builder = builder.header(*k, *v);
+            }
+            self.responses
+                .lock()
+                .unwrap()
+                .push_back(Ok(builder.body(body.to_vec()).unwrap()));
+        }
+
+        fn push_err(&self, kind: ErrorKind) {
+            self.responses.lock().unwrap().push_back(Err(kind.into()));
+        }
+    }
+
+    #[async_trait]
+    impl Client for &MockClient {
+        type RespondError = IoError;
+
+        async fn respond(
+            &self,
+            request: Request<Body>,
+        ) -> Result<Response<Body>, Self::RespondError> {
+            self.uris.lock().unwrap().push(request.uri().to_string());
+            self.responses
+                .lock()
+                .unwrap()
+                .pop_front()
+                .expect("no response")
+        }
+    }
+
+    const KEY_INVALID: &[u8] =
+        include_bytes!("../../tests/response_body_json_files/json_err_3.json");
+    const SUCCESS: &[u8] = include_bytes!("../../tests/response_body_json_files/json_default.json");
+
+    #[test]
+    fn test_fallback_and_recover() {
+        let mock = MockClient::default();
+        let modes = Arc::new(Mutex::new(vec![]));
+        let modes_cb = modes.clone();
+        let client = FallbackClient::new(&mock, "foo".into())
+            .probe_interval(Duration::ZERO)
+            .on_mode_change(move |mode| modes_cb.lock().unwrap().push(mode));
+
+        let json = Json::new("24.48.0.1", None);
+
+        block_on(async {
+            // pro fails, retried on the free endpoint
+            mock.push(403, &[], KEY_INVALID);
+            mock.push(200, &[("X-Rl", "44"), ("X-Ttl", "60")], SUCCESS);
+            let (res, rate_limit) = client.respond_endpoint(&json).await.unwrap();
+            assert!(matches!(res, JsonResponseBodyJson::Success(_)));
+            assert_eq!(rate_limit.unwrap().remaining, Some(44));
+            assert_eq!(client.mode(), FallbackMode::Free);
+            assert_eq!(client.free_requests(), 1);
+
+            // probe still fails
+            mock.push(403, &[], KEY_INVALID);
+            mock.push(200, &[("X-Rl", "0"), ("X-Ttl", "60")], SUCCESS);
+            client.respond_endpoint(&json).await.unwrap();
+            assert_eq!(client.mode(), FallbackMode::Free);
+
+            // free limiter engaged
+            mock.push(403, &[], KEY_INVALID);
+            match client.respond_endpoint(&json).await {
+                Err(FallbackError::RateLimited { retry_in }) => {
+                    assert!(retry_in <= Duration::from_secs(60))
+                }
+                ret => panic!("{:?}", ret),
+            }
+
+            // probe succeeds
+            mock.push(200, &[], br#"{"status":"success"}"#);
+            mock.push(200, &[], SUCCESS);
+            let (_, rate_limit) = client.respond_endpoint(&json).await.unwrap();
+            assert!(rate_limit.is_none());
+            assert_eq!(client.mode(), FallbackMode::Pro);
+        });
+
+        assert_eq!(
+            *modes.lock().unwrap(),
+            vec![FallbackMode::Free, FallbackMode::Pro]
+        );
+        assert_eq!(
+            *mock.uris.lock().unwrap(),
+            vec![
+                "https://pro.ip-api.com/json/24.48.0.1?key=foo",
+                "http://ip-api.com/json/24.48.0.1",
+                "https://pro.ip-api.com/json/?key=foo&fields=status%2Cmessage",
+                "http://ip-api.com/json/24.48.0.1",
+                "https://pro.ip-api.com/json/?key=foo&fields=status%2Cmessage",
+                "https://pro.ip-api.com/json/?key=foo&fields=status%2Cmessage",
+                "https://pro.ip-api.com/json/24.48.0.1?key=foo",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_probe_with_respond_error() {
+        let mock = MockClient::default();
+        let client = FallbackClient::new(&mock, "foo".into()).probe_interval(Duration::ZERO);
+
+        block_on(async {
+            // A network error keeps the key.
+            mock.push_err(ErrorKind::ConnectionRefused);
+            match client.probe().await {
+                Err(ClientRespondEndpointError::RespondFailed(err)) => {
+                    assert_eq!(err.kind(), ErrorKind::ConnectionRefused)
+                }
+                ret => panic!("{:?}", ret),
+            }
+            assert_eq!(client.mode(), FallbackMode::Pro);
+
+            mock.push(403, &[], KEY_INVALID);
+            assert_eq!(client.probe().await.unwrap(), FallbackMode::Free);
+
+            // And does not recover either, the free endpoint is still used.
+            mock.push_err(ErrorKind::TimedOut);
+            mock.push(200, &[("X-Rl", "44"), ("X-Ttl", "60")], SUCCESS);
+            client
+                .respond_endpoint(&Json::new("24.48.0.1", None))
+                .await
+                .unwrap();
+            assert_eq!(client.mode(), FallbackMode::Free);
+            assert_eq!(client.free_requests(), 1);
+
+            mock.push(200, &[], br#"{"status":"success"}"#);
+            assert_eq!(client.probe().await.unwrap(), FallbackMode::Pro);
+        });
+    }
+}
@@ -0,0 +1,45 @@
+pub mod bulk;
+#[cfg(feature = "axum")]
+pub mod extract;
+pub mod fallback;
+#[cfg(feature = "offline")]
+pub mod offline;
+#[cfg(feature = "tower")]
+pub mod service;
+
+use crate::{
+    endpoints::{
+        batch::{Batch, LenientBatch},
+        json::Json,
+    },
+    types::api_key::ApiKey,
+};
+
+//
+pub trait KeyedEndpoint {
+    fn with_key(&self, key: Option<ApiKey>) -> Self;
+}
+
+impl KeyedEndpoint for Json {
+    fn with_key(&self, key: Option<ApiKey>) -> Self {
+        Self {
+            key,
+            ..self.clone()
+        }
+    }
+}
+
+impl KeyedEndpoint for Batch {
+    fn with_key(&self, key: Option<ApiKey>) -> Self {
+        Self {
+            key,
+            ..self.clone()
+        }
+    }
+}
+
+impl KeyedEndpoint for LenientBatch {
+    fn with_key(&self, key: Option<ApiKey>) -> Self {
+        Self(self.0.with_key(key))
+    }
+}
@@ -0,0 +1,615 @@
+//! A `Client` answering `Json` and `Batch` requests from local data instead of ip-api.com, e.g.
+//! for tests and air-gapped hosts.
+//!
+//! Data is prefixes to records, from JSON lines (`LookupCache` files included), CSV or an MMDB,
+//! the most specific prefix wins. Like ip-api.com, private and reserved ranges and anything that
+//! is not an IP address fail, and so does an address without data. `fields` is applied as ip-api
+//! does, `status` is always kept.
+
+use core::{fmt, str::FromStr as _};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read},
+    net::IpAddr,
+    path::Path,
+};
+
+use http_api_client::{
+    Body, Client, Request, Response, async_trait,
+    http::{StatusCode, header::CONTENT_TYPE},
+};
+use ipnet::IpNet;
+use maxminddb::{MaxMindDBError, Reader, geoip2};
+use serde::Deserialize;
+use serde_json::{Error as SerdeJsonError, Map, Value};
+
+use crate::{
+    endpoints::{
+        fields::{DEFAULT_FIELDS, field_names},
+        json::JsonResponseBodyJson,
+    },
+    prefix::{Prefix, PrefixParseError},
+    types::lang::Lang,
+};
+
+pub const MESSAGE_PRIVATE_RANGE: &str = "private range";
+pub const MESSAGE_RESERVED_RANGE: &str = "reserved range";
+pub const MESSAGE_INVALID_QUERY: &str = "invalid query";
+/// Not something ip-api.com says, its `FailReason` is `Other`.
+pub const MESSAGE_NOT_FOUND: &str = "not found";
+
+pub const PRIVATE_RANGES: &[&str] = &["10.0.0.0/8", "172.16.0.0/12", "192.168.0.0/16", "fc00::/7"];
+pub const RESERVED_RANGES: &[&str] = &[
+    "0.0.0.0/8",
+    "100.64.0.0/10",
+    "127.0.0.0/8",
+    "169.254.0.0/16",
+    "192.0.0.0/24",
+    "192.0.2.0/24",
+    "198.18.0.0/15",
+    "198.51.100.0/24",
+    "203.0.113.0/24",
+    "224.0.0.0/4",
+    "240.0.0.0/4",
+    "::/128",
+    "::1/128",
+    "2001:db8::/32",
+    "fe80::/10",
+    "ff00::/8",
+];
+
+/// CSV columns holding the prefix, any other column is an ip-api field.
+pub const CSV_PREFIX_COLUMNS: &[&str] = &["prefix", "network", "ip"];
+
+//
+pub struct OfflineClient {
+    records: BTreeMap<u8, HashMap<IpNet, JsonResponseBodyJson>>,
+    mmdb: Option<Reader<Vec<u8>>>,
+    private_ranges: Vec<IpNet>,
+    reserved_ranges: Vec<IpNet>,
+}
+
+impl fmt::Debug for OfflineClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OfflineClient")
+            .field("records", &self.len())
+            .field(
+                "mmdb",
+                &self.mmdb.as_ref().map(|x| &x.metadata.database_type),
+            )
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for OfflineClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonLine {
+    #[serde(alias = "ip")]
+    prefix: Box<str>,
+    #[serde(alias = "result")]
+    record: JsonResponseBodyJson,
+}
+
+impl OfflineClient {
+    /// Without data, every public address fails with `MESSAGE_NOT_FOUND`.
+    pub fn new() -> Self {
+        let parse = |ranges: &[&str]| ranges.iter().map(|x| x.parse().expect("Never")).collect();
+        Self {
+            records: BTreeMap::new(),
+            mmdb: None,
+            private_ranges: parse(PRIVATE_RANGES),
+            reserved_ranges: parse(RESERVED_RANGES),
+        }
+    }
+
+    /// By extension, `.mmdb`, `.csv`, or JSON lines for `.jsonl`, `.ndjson` and `.json`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, OfflineError> {
+        let path = path.as_ref();
+        match path.extension().and_then(|x| x.to_str()) {
+            Some("mmdb") => Self::new().mmdb(fs::read(path).map_err(OfflineError::Io)?),
+            Some("csv") => Self::new().csv(File::open(path).map_err(OfflineError::Io)?),
+            Some("jsonl" | "ndjson" | "json") => {
+                Self::new().json_lines(BufReader::new(File::open(path).map_err(OfflineError::Io)?))
+            }
+            _ => Err(OfflineError::FormatUnknown(
+                path.display().to_string().into(),
+            )),
+        }
+    }
+
+    /// `{"prefix":..,"record":..}` per line, or `{"ip":..,"result":..}` as in `LookupCache`.
+    pub fn json_lines(mut self, reader: impl BufRead) -> Result<Self, OfflineError> {
+        for (i, line) in reader.lines().enumerate() {
+            let line = line.map_err(OfflineError::Io)?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let JsonLine { prefix, record } =
+                serde_json::from_str(&line).map_err(|err| OfflineError::JsonLine(i + 1, err))?;
+            let prefix = prefix.parse::<Prefix>().map_err(OfflineError::Prefix)?;
+            self.insert(prefix, record);
+        }
+        Ok(self)
+    }
+
+    /// A header row with one of `CSV_PREFIX_COLUMNS` and ip-api field names, e.g.
+    /// `prefix,countryCode,lat,lon,as`. `status` defaults to success.
+    pub fn csv(mut self, reader: impl Read) -> Result<Self, OfflineError> {
+        let mut reader = csv::Reader::from_reader(reader);
+        let headers = reader.headers().map_err(OfflineError::Csv)?.clone();
+        let prefix_column = headers
+            .iter()
+            .position(|x| CSV_PREFIX_COLUMNS.contains(&x))
+            .ok_or(OfflineError::PrefixColumnMissing)?;
+
+        for row in reader.records() {
+            let row = row.map_err(OfflineError::Csv)?;
+            let mut map = Map::new();
+            map.insert("status".to_owned(), "success".into());
+            for (i, (k, v)) in headers.iter().zip(row.iter()).enumerate() {
+                if i == prefix_column || v.is_empty() {
+                    continue;
+                }
+                let v = match k {
+                    "lat" | "lon" | "offset" => v.parse().map(Value::Number).ok(),
+                    "mobile" | "proxy" | "hosting" => v.parse().map(Value::Bool).ok(),
+                    _ => None,
+                }
+                .unwrap_or_else(|| v.into());
+                map.insert(k.to_owned(), v);
+            }
+            let record = serde_json::from_value(Value::Object(map)).map_err(OfflineError::Json)?;
+            let prefix = row[prefix_column]
+                .parse::<Prefix>()
+                .map_err(OfflineError::Prefix)?;
+            self.insert(prefix, record);
+        }
+        Ok(self)
+    }
+
+    /// GeoLite2-City, optionally with GeoLite2-ASN fields, e.g. from `MmdbWriter`. Consulted when
+    /// no inserted prefix matches.
+    pub fn mmdb(mut self, buf: Vec<u8>) -> Result<Self, OfflineError> {
+        self.mmdb = Some(Reader::from_source(buf).map_err(OfflineError::Mmdb)?);
+        Ok(self)
+    }
+
+    /// `query` is set to the address looked up.
+    pub fn insert(&mut self, prefix: impl Into<Prefix>, record: JsonResponseBodyJson) {
+        for net in prefix.into().nets() {
+            self.records
+                .entry(net.prefix_len())
+                .or_default()
+                .insert(net, record.clone());
+        }
+    }
+
+    /// Inserted prefixes, the MMDB not included.
+    pub fn len(&self) -> usize {
+        self.records.values().map(HashMap::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// All fields, `lang` picks the MMDB names.
+    pub fn lookup(&self, query: &str, lang: Lang) -> Result<JsonResponseBodyJson, OfflineError> {
+        let fail = |message: &str| {
+            serde_json::from_value(serde_json::json!({
+                "status": "fail",
+                "message": message,
+                "query": query,
+            }))
+            .map_err(OfflineError::Json)
+        };
+
+        let Ok(ip) = query.trim().parse::<IpAddr>() else {
+            return fail(MESSAGE_INVALID_QUERY);
+        };
+        if self.private_ranges.iter().any(|x| x.contains(&ip)) {
+            return fail(MESSAGE_PRIVATE_RANGE);
+        }
+        if self.reserved_ranges.iter().any(|x| x.contains(&ip)) {
+            return fail(MESSAGE_RESERVED_RANGE);
+        }
+
+        let max_len = if ip.is_ipv6() { 128 } else { 32 };
+        for (len, records) in self.records.range(..=max_len).rev() {
+            let net = IpNet::new(ip, *len).expect("Never").trunc();
+            if let Some(record) = records.get(&net) {
+                let mut record = record.clone();
+                match &mut record {
+                    JsonResponseBodyJson::Success(x) => {
+                        x.query = ip;
+                        x.present.insert("query");
+                    }
+                    JsonResponseBodyJson::Fail(x) => x.query = query.into(),
+                }
+                return Ok(record);
+            }
+        }
+
+        if let Some(reader) = &self.mmdb
+            && let Some(record) = mmdb_record(reader, ip, &lang.to_string())?
+        {
+            return Ok(record);
+        }
+
+        fail(MESSAGE_NOT_FOUND)
+    }
+
+    fn respond_sync(&self, request: Request<Body>) -> Result<Response<Body>, OfflineError> {
+        let params =
+            url::form_urlencoded::parse(request.uri().query().unwrap_or_default().as_bytes())
+                .collect::<HashMap<_, _>>();
+        let fields = params.get("fields").map(|x| x.as_ref());
+        let lang = params.get("lang").and_then(|x| Lang::from_str(x).ok());
+
+        let path = request.uri().path();
+        let body = if let Some(query) = path
+            .strip_prefix("/json")
+            .map(|x| x.trim_start_matches('/'))
+        {
+            self.render(query, fields, lang)?
+        } else if path == "/batch" {
+            let Ok(queries) = serde_json::from_slice::<Vec<Value>>(request.body()) else {
+                return Ok(response(
+                    StatusCode::BAD_REQUEST,
+                    b"invalid request".to_vec(),
+                ));
+            };
+            let mut results = vec![];
+            for query in &queries {
+                // A string, or an object with its own `fields` and `lang`.
+                let (query, fields, lang) = match query {
+                    Value::Object(x) => (
+                        x.get("query").and_then(Value::as_str).unwrap_or_default(),
+                        x.get("fields").and_then(Value::as_str).or(fields),
+                        x.get("lang")
+                            .and_then(Value::as_str)
+                            .and_then(|x| Lang::from_str(x).ok())
+                            .or(lang),
+                    ),
+                    x => (x.as_str().unwrap_or_default(), fields, lang),
+                };
+                results.push(self.render(query, fields, lang)?);
+            }
+            Value::Array(results)
+        } else {
+            return Ok(response(
+                StatusCode::NOT_FOUND,
+                b"404 page not found".to_vec(),
+            ));
+        };
+
+        Ok(response(
+            StatusCode::OK,
+            serde_json::to_vec(&body).map_err(OfflineError::Json)?,
+        ))
+    }
+
+    fn render(
+        &self,
+        query: &str,
+        fields: Option<&str>,
+        lang: Option<Lang>,
+    ) -> Result<Value, OfflineError> {
+        let result = self.lookup(query, lang.unwrap_or(Lang::EN))?;
+        let fields = match fields {
+            Some(fields) => field_names(fields),
+            None => DEFAULT_FIELDS.iter().map(|x| (*x).into()).collect(),
+        };
+
+        let Ok(Value::Object(mut map)) = serde_json::to_value(&result) else {
+            unreachable!("results serialize to objects")
+        };
+        // As ip-api.com, a fail always explains itself.
+        let keep: &[&str] = if result.is_success() {
+            &["status"]
+        } else {
+            &["status", "message", "query"]
+        };
+        map.retain(|k, _| keep.contains(&k.as_str()) || fields.iter().any(|x| **x == *k));
+        Ok(Value::Object(map))
+    }
+}
+
+fn response(status: StatusCode, body: Vec<u8>) -> Response<Body> {
+    let mut response = Response::new(body);
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        CONTENT_TYPE,
+        "application/json; charset=utf-8".parse().expect("Never"),
+    );
+    response
+}
+
+fn mmdb_record(
+    reader: &Reader<Vec<u8>>,
+    ip: IpAddr,
+    lang: &str,
+) -> Result<Option<JsonResponseBodyJson>, OfflineError> {
+    let city = match reader.lookup::<geoip2::City>(ip) {
+        Ok(x) => x,
+        Err(MaxMindDBError::AddressNotFoundError(_)) => return Ok(None),
+        Err(err) => return Err(OfflineError::Mmdb(err)),
+    };
+    let asn = reader
+        .lookup::<geoip2::Asn>(ip)
+        .map_err(OfflineError::Mmdb)?;
+
+    fn localized<'a>(names: &Option<BTreeMap<&str, &'a str>>, lang: &str) -> Option<&'a str> {
+        let names = names.as_ref()?;
+        names.get(lang).or_else(|| names.get("en")).copied()
+    }
+    let name = |names| localized(names, lang);
+    let mut map = Map::new();
+    let mut put = |k: &str, v: Option<Value>| {
+        if let Some(v) = v {
+            map.insert(k.to_owned(), v);
+        }
+    };
+    put("status", Some("success".into()));
+    put("query", Some(ip.to_string().into()));
+    if let Some(x) = &city.continent {
+        put("continent", name(&x.names).map(Into::into));
+        put("continentCode", x.code.map(Into::into));
+    }
+    if let Some(x) = &city.country {
+        put("country", name(&x.names).map(Into::into));
+        put("countryCode", x.iso_code.map(Into::into));
+    }
+    if let Some(x) = city.subdivisions.as_ref().and_then(|x| x.first()) {
+        put("region", x.iso_code.map(Into::into));
+        put("regionName", name(&x.names).map(Into::into));
+    }
+    if let Some(x) = &city.city {
+        put("city", name(&x.names).map(Into::into));
+    }
+    if let Some(x) = &city.postal {
+        put("zip", x.code.map(Into::into));
+    }
+    if let Some(x) = &city.location {
+        put("lat", x.latitude.map(Into::into));
+        put("lon", x.longitude.map(Into::into));
+        put("timezone", x.time_zone.map(Into::into));
+    }
+    if let Some(n) = asn.autonomous_system_number {
+        let r#as = match asn.autonomous_system_organization {
+            Some(org) => format!("AS{} {}", n, org),
+            None => format!("AS{}", n),
+        };
+        put("as", Some(r#as.into()));
+    }
+
+    serde_json::from_value(Value::Object(map))
+        .map(Some)
+        .map_err(OfflineError::Json)
+}
+
+#[async_trait]
+impl Client for OfflineClient {
+    type RespondError = OfflineError;
+
+    async fn respond(&self, request: Request<Body>) -> Result<Response<Body>, Self::RespondError> {
+        self.respond_sync(request)
+    }
+}
+
+//
+#[derive(Debug)]
+pub enum OfflineError {
+    Io(io::Error),
+    /// 1-based line number.
+    JsonLine(usize, SerdeJsonError),
+    Json(SerdeJsonError),
+    Csv(csv::Error),
+    PrefixColumnMissing,
+    Prefix(PrefixParseError),
+    Mmdb(MaxMindDBError),
+    FormatUnknown(Box<str>),
+}
+
+impl fmt::Display for OfflineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for OfflineError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        endpoints::{
+            batch::{Batch, BatchQuery},
+            json::{FailReason, Json},
+        },
+        mmdb::MmdbWriter,
+    };
+
+    const FULL_FIELDS: &str =
+        include_str!("../../tests/response_body_json_files/json_full_fields.json");
+
+    fn client() -> OfflineClient {
+        let full = serde_json::from_str::<Value>(FULL_FIELDS).unwrap();
+        let lines = format!(
+            "{}\n\n{}\n",
+            serde_json::json!({"prefix": "24.48.0.0/16", "record": full}),
+            r#"{"ip":"24.48.0.1","result":{"status":"success","countryCode":"US","query":"24.48.0.1"}}"#,
+        );
+        OfflineClient::new().json_lines(lines.as_bytes()).unwrap()
+    }
+
+    async fn json(client: &OfflineClient, json: Json) -> JsonResponseBodyJson {
+        client.respond_endpoint(&json).await.unwrap().0
+    }
+
+    #[tokio::test]
+    async fn test_json() {
+        let client = client();
+        assert_eq!(client.len(), 2);
+
+        let result = json(&client, Json::new("24.48.200.9", None)).await;
+        let success = result.as_success().unwrap();
+        assert_eq!(success.query, "24.48.200.9".parse::<IpAddr>().unwrap());
+        assert_eq!(success.country_code.to_string(), "CA");
+        assert_eq!(success.zip.as_ref(), "H3G");
+        // Not in the default fields.
+        assert!(success.asname.is_empty());
+        assert!(!success.extra.contains_key("countryCode3"));
+
+        // More specific.
+        let result = json(&client, Json::new("24.48.0.1", None)).await;
+        assert_eq!(result.as_success().unwrap().country_code.to_string(), "US");
+
+        let request = Request::get("/json/24.48.0.2?fields=24578")
+            .body(vec![])
+            .unwrap();
+        let response = client.respond(request).await.unwrap();
+        assert_eq!(
+            serde_json::from_slice::<Value>(response.body()).unwrap(),
+            serde_json::json!({"status": "success", "countryCode": "CA", "query": "24.48.0.2"})
+        );
+
+        let request = Request::get("/csv/24.48.0.2").body(vec![]).unwrap();
+        let response = client.respond(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        for (query, reason) in [
+            ("10.0.0.1", FailReason::PrivateRange),
+            ("fd00::1", FailReason::PrivateRange),
+            ("127.0.0.1", FailReason::ReservedRange),
+            ("example.com", FailReason::InvalidQuery),
+            ("", FailReason::InvalidQuery),
+            ("8.8.8.8", FailReason::Other),
+        ] {
+            let result = json(&client, Json::new(query, None)).await;
+            let fail = result.as_fail().unwrap();
+            assert_eq!(fail.reason(), reason, "{}", query);
+            assert_eq!(fail.query.as_ref(), query);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batch() {
+        let client = client();
+        let batch = Batch::new(
+            vec![
+                BatchQuery::new("24.48.0.1"),
+                BatchQuery::new("10.0.0.1"),
+                BatchQuery::new("24.48.0.2").fields("country"),
+            ],
+            None,
+        )
+        .fields("status,countryCode,query");
+        let (results, _) = client.respond_endpoint(&batch).await.unwrap();
+
+        let [first, second, third] = &results.0[..] else {
+            panic!("{:?}", results)
+        };
+        let first = first.as_success().unwrap();
+        assert_eq!(first.country_code.to_string(), "US");
+        assert!(first.country.is_empty());
+        assert_eq!(second.as_fail().unwrap().reason(), FailReason::PrivateRange);
+        let third = third.as_success().unwrap();
+        assert_eq!(third.country.as_ref(), "Canada");
+        assert!(third.country_code.to_string().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_csv() {
+        let csv = "\
+prefix,countryCode,lat,lon,proxy,as
+24.48.0.0/16,CA,45.4995,-73.5848,true,AS5769 Videotron Telecom Ltee
+2001:db8:1::-2001:db8:1::ff,US,,,,
+";
+        let client = OfflineClient::new().csv(csv.as_bytes()).unwrap();
+
+        let result = json(&client, Json::new("24.48.0.1", None).fields("66846719")).await;
+        let success = result.as_success().unwrap();
+        assert_eq!(success.lat, 45.4995);
+        assert!(success.proxy);
+        assert_eq!(success.asn(), Some(5769.into()));
+
+        // Fields the record does not have are left out, not made up.
+        let request =
+            Request::get("/json/24.48.0.1?fields=continentCode,currentTime,countryCode,query")
+                .body(vec![])
+                .unwrap();
+        let response = client.respond(request).await.unwrap();
+        assert_eq!(
+            serde_json::from_slice::<Value>(response.body()).unwrap(),
+            serde_json::json!({"status": "success", "countryCode": "CA", "query": "24.48.0.1"})
+        );
+
+        // Documentation range, reserved before any data.
+        let result = json(&client, Json::new("2001:db8:1::1", None)).await;
+        assert_eq!(
+            result.as_fail().unwrap().reason(),
+            FailReason::ReservedRange
+        );
+
+        assert!(matches!(
+            OfflineClient::new().csv("countryCode\nCA\n".as_bytes()),
+            Err(OfflineError::PrefixColumnMissing)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_mmdb() {
+        let full = serde_json::from_str::<JsonResponseBodyJson>(FULL_FIELDS).unwrap();
+        let mut writer = MmdbWriter::new().lang(Lang::DE);
+        writer.insert(
+            "24.48.0.0/16".parse::<Prefix>().unwrap(),
+            full.as_success().unwrap(),
+        );
+        let client = OfflineClient::new().mmdb(writer.to_vec()).unwrap();
+
+        let result = json(&client, Json::new("24.48.0.1", None).lang(Lang::DE)).await;
+        let success = result.as_success().unwrap();
+        let full = full.as_success().unwrap();
+        assert_eq!(success.country_code, full.country_code);
+        assert_eq!(success.region_name, full.region_name);
+        assert_eq!(success.city, full.city);
+        assert_eq!((success.lat, success.lon), (full.lat, full.lon));
+        assert_eq!(success.timezone, full.timezone);
+        assert_eq!(success.r#as, full.r#as);
+
+        let result = json(&client, Json::new("24.49.0.1", None)).await;
+        assert_eq!(
+            result.as_fail().unwrap().message.as_ref(),
+            MESSAGE_NOT_FOUND
+        );
+    }
+
+    #[test]
+    fn test_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lookups.jsonl");
+        let mut cache = crate::lookup_cache::LookupCache::open(&path).unwrap();
+        cache
+            .insert(
+                "24.48.0.1".parse().unwrap(),
+                serde_json::from_str(FULL_FIELDS).unwrap(),
+            )
+            .unwrap();
+        drop(cache);
+
+        let client = OfflineClient::open(&path).unwrap();
+        assert!(client.lookup("24.48.0.1", Lang::EN).unwrap().is_success());
+        assert!(matches!(
+            OfflineClient::open(dir.path().join("x.txt")),
+            Err(OfflineError::FormatUnknown(_))
+        ));
+    }
+}
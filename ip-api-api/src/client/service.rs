@@ -0,0 +1,533 @@
+//! `tower::Service` adapters, stack them with the layers re-exported here or any other tower middleware.
+//!
+//! Responses carry the `X-Rl`/`X-Ttl` rate limit as `respond_endpoint` does, `None` with a key.
+
+use core::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use http_api_client::Client;
+use tower::{BoxError, Layer, Service, buffer::Buffer};
+pub use tower::{limit::RateLimitLayer, timeout::TimeoutLayer};
+
+use crate::{
+    client::fallback::RespondError,
+    endpoints::{
+        batch::{Batch, BatchQuery, BatchResponseBodyJson, MAX_QUERY},
+        json::{FailReason, Json, JsonResponseBodyJson},
+    },
+    objects::rate_limit::RateLimit,
+    types::{api_key::ApiKey, lang::Lang},
+};
+
+/// Free endpoint limit, see the `X-Rl` and `X-Ttl` headers.
+pub const FREE_REQUESTS_PER_MINUTE: u64 = 45;
+
+pub type BoxFuture<T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send>>;
+
+/// Response of `LookupService`.
+pub type LookupResponse = (JsonResponseBodyJson, Option<RateLimit>);
+
+/// Rate limit matching the free endpoint.
+pub fn free_rate_limit_layer() -> RateLimitLayer {
+    RateLimitLayer::new(FREE_REQUESTS_PER_MINUTE, Duration::from_secs(60))
+}
+
+//
+#[derive(Debug)]
+pub struct LookupService<C> {
+    client: Arc<C>,
+    key: Option<ApiKey>,
+    fields: Option<Box<str>>,
+    lang: Option<Lang>,
+}
+
+impl<C> Clone for LookupService<C> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            key: self.key.clone(),
+            fields: self.fields.clone(),
+            lang: self.lang,
+        }
+    }
+}
+
+impl<C> LookupService<C> {
+    pub fn new(client: C, key: Option<ApiKey>) -> Self {
+        Self {
+            client: Arc::new(client),
+            key,
+            fields: None,
+            lang: None,
+        }
+    }
+
+    pub fn fields(mut self, fields: impl AsRef<str>) -> Self {
+        self.fields = Some(fields.as_ref().into());
+        self
+    }
+
+    pub fn lang(mut self, lang: Lang) -> Self {
+        self.lang = Some(lang);
+        self
+    }
+
+    /// Same client and options, `Service<Vec<IpAddr>>` through the `Batch` endpoint.
+    pub fn batch(&self) -> BatchLookupService<C> {
+        BatchLookupService(self.clone())
+    }
+
+    fn json(&self, ip: IpAddr) -> Json {
+        let mut json = Json::new(ip.to_string(), self.key.clone());
+        json.fields = self.fields.clone();
+        json.lang = self.lang;
+        json
+    }
+}
+
+impl<C> Service<IpAddr> for LookupService<C>
+where
+    C: Client + Send + Sync + 'static,
+{
+    type Response = LookupResponse;
+    type Error = RespondError<C::RespondError>;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, ip: IpAddr) -> Self::Future {
+        let client = self.client.clone();
+        let json = self.json(ip);
+        Box::pin(async move { client.respond_endpoint(&json).await })
+    }
+}
+
+//
+#[derive(Debug)]
+pub struct BatchLookupService<C>(LookupService<C>);
+
+impl<C> Clone for BatchLookupService<C> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<C> Service<Vec<IpAddr>> for BatchLookupService<C>
+where
+    C: Client + Send + Sync + 'static,
+{
+    type Response = (BatchResponseBodyJson, Option<RateLimit>);
+    type Error = RespondError<C::RespondError>;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    /// More than `MAX_QUERY` addresses are sent as consecutive batches, the rate limit is the one
+    /// after the last. Each batch counts against the rate limit, see `client::bulk` to wait it out.
+    fn call(&mut self, ips: Vec<IpAddr>) -> Self::Future {
+        let client = self.0.client.clone();
+        let batches = ips
+            .chunks(MAX_QUERY)
+            .map(|chunk| {
+                let mut batch = Batch::new(
+                    chunk
+                        .iter()
+                        .map(|x| BatchQuery::new(x.to_string()))
+                        .collect(),
+                    self.0.key.clone(),
+                );
+                batch.fields = self.0.fields.clone();
+                batch.lang = self.0.lang;
+                batch
+            })
+            .collect::<Vec<_>>();
+        Box::pin(async move {
+            let mut results = Vec::with_capacity(ips.len());
+            let mut rate_limit = None;
+            for batch in &batches {
+                let (body, x) = client.respond_endpoint(batch).await?;
+                results.extend(body.0);
+                rate_limit = x;
+            }
+            Ok((BatchResponseBodyJson(results), rate_limit))
+        })
+    }
+}
+
+//
+//
+/// Requests waiting for the inner service, e.g. behind a rate limit.
+pub const CACHE_BUFFER_BOUND_DEFAULT: usize = 64;
+
+//
+#[derive(Debug, Copy, Clone)]
+pub struct CacheLayer {
+    ttl: Duration,
+    capacity: usize,
+    bound: usize,
+}
+
+impl CacheLayer {
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            ttl,
+            capacity,
+            bound: CACHE_BUFFER_BOUND_DEFAULT,
+        }
+    }
+
+    pub fn bound(mut self, bound: usize) -> Self {
+        self.bound = bound;
+        self
+    }
+}
+
+/// The inner service runs behind a `Buffer`, so layering must happen on a Tokio runtime.
+impl<S> Layer<S> for CacheLayer
+where
+    S: Service<IpAddr, Response = LookupResponse> + Send + 'static,
+    S::Future: Send + 'static,
+    S::Error: Into<BoxError> + Send + Sync,
+{
+    type Service = CacheService<S::Future>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CacheService {
+            inner: Buffer::new(inner, self.bound),
+            ttl: self.ttl,
+            capacity: self.capacity,
+            entries: Default::default(),
+        }
+    }
+}
+
+/// Caches successes and private/reserved range fails, other fails may change on retry.
+///
+/// Misses are queued for the inner service, hits never wait on it, e.g. on a rate limit below.
+pub struct CacheService<F> {
+    inner: Buffer<IpAddr, F>,
+    ttl: Duration,
+    capacity: usize,
+    entries: Arc<Mutex<HashMap<IpAddr, (JsonResponseBodyJson, Instant)>>>,
+}
+
+impl<F> fmt::Debug for CacheService<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CacheService")
+            .field("ttl", &self.ttl)
+            .field("capacity", &self.capacity)
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F: Send + 'static> Clone for CacheService<F> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            ttl: self.ttl,
+            capacity: self.capacity,
+            entries: self.entries.clone(),
+        }
+    }
+}
+
+impl<F> CacheService<F> {
+    pub fn len(&self) -> usize {
+        self.entries.lock().expect("Never").len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().expect("Never").clear()
+    }
+
+    fn get(&self, ip: &IpAddr) -> Option<JsonResponseBodyJson> {
+        let mut entries = self.entries.lock().expect("Never");
+        match entries.get(ip) {
+            Some((json, at)) if at.elapsed() < self.ttl => Some(json.clone()),
+            Some(_) => {
+                entries.remove(ip);
+                None
+            }
+            None => None,
+        }
+    }
+}
+
+fn insert(
+    entries: &Mutex<HashMap<IpAddr, (JsonResponseBodyJson, Instant)>>,
+    ttl: Duration,
+    capacity: usize,
+    ip: IpAddr,
+    json: &JsonResponseBodyJson,
+) {
+    let cacheable = match json {
+        JsonResponseBodyJson::Success(_) => true,
+        JsonResponseBodyJson::Fail(x) => {
+            matches!(
+                x.reason(),
+                FailReason::PrivateRange | FailReason::ReservedRange
+            )
+        }
+    };
+    if !cacheable || capacity == 0 {
+        return;
+    }
+
+    let mut entries = entries.lock().expect("Never");
+    if entries.len() >= capacity && !entries.contains_key(&ip) {
+        entries.retain(|_, (_, at)| at.elapsed() < ttl);
+    }
+    if entries.len() >= capacity
+        && !entries.contains_key(&ip)
+        && let Some(oldest) = entries
+            .iter()
+            .min_by_key(|(_, (_, at))| *at)
+            .map(|(k, _)| *k)
+    {
+        entries.remove(&oldest);
+    }
+    entries.insert(ip, (json.clone(), Instant::now()));
+}
+
+/// Hits have no rate limit, no request was made.
+impl<F, E> Service<IpAddr> for CacheService<F>
+where
+    F: Future<Output = Result<LookupResponse, E>> + Send + 'static,
+    E: Into<BoxError>,
+{
+    type Response = LookupResponse;
+    type Error = BoxError;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, ip: IpAddr) -> Self::Future {
+        if let Some(json) = self.get(&ip) {
+            return Box::pin(async move { Ok((json, None)) });
+        }
+
+        let mut inner = self.inner.clone();
+        let entries = self.entries.clone();
+        let (ttl, capacity) = (self.ttl, self.capacity);
+        Box::pin(async move {
+            core::future::poll_fn(|cx| inner.poll_ready(cx)).await?;
+            let (json, rate_limit) = inner.call(ip).await?;
+            insert(&entries, ttl, capacity, ip, &json);
+            Ok((json, rate_limit))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Error as IoError;
+
+    use http_api_client::{Body, Request, Response, async_trait};
+    use tower::{ServiceBuilder, ServiceExt as _, timeout::error::Elapsed};
+    use tower_test::mock;
+
+    const SUCCESS: &[u8] = include_bytes!("../../tests/response_body_json_files/json_default.json");
+
+    #[derive(Default)]
+    struct MockClient {
+        uris: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl Client for MockClient {
+        type RespondError = IoError;
+
+        async fn respond(
+            &self,
+            request: Request<Body>,
+        ) -> Result<Response<Body>, Self::RespondError> {
+            self.uris.lock().unwrap().push(request.uri().to_string());
+            let body = if request.uri().path() == "/batch" {
+                let queries = serde_json::from_slice::<Vec<serde_json::Value>>(request.body())
+                    .unwrap()
+                    .into_iter()
+                    .map(|x| serde_json::json!({"status": "success", "query": x}))
+                    .collect::<Vec<_>>();
+                serde_json::to_vec(&queries).unwrap()
+            } else {
+                SUCCESS.to_vec()
+            };
+            Ok(Response::builder()
+                .header("X-Rl", "44")
+                .header("X-Ttl", "60")
+                .body(body)
+                .unwrap())
+        }
+    }
+
+    fn ip() -> IpAddr {
+        "24.48.0.1".parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_lookup_service() {
+        let svc = LookupService::new(MockClient::default(), None).fields("status,query");
+
+        let (json, rate_limit) = svc.clone().oneshot(ip()).await.unwrap();
+        assert!(json.is_success());
+        assert_eq!(rate_limit.unwrap().remaining, Some(44));
+
+        let (batch, rate_limit) = svc.batch().oneshot(vec![ip(), ip()]).await.unwrap();
+        assert_eq!(batch.len(), 2);
+        assert_eq!(rate_limit.unwrap().seconds_until_reset, Some(60));
+
+        assert_eq!(
+            *svc.client.uris.lock().unwrap(),
+            [
+                "http://ip-api.com/json/24.48.0.1?fields=status%2Cquery",
+                "http://ip-api.com/batch?fields=status%2Cquery"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_batch_lookup_service_chunks() {
+        let svc = LookupService::new(MockClient::default(), None);
+        let ips = (0..250u8)
+            .map(|x| IpAddr::from([24, 48, 0, x]))
+            .collect::<Vec<_>>();
+
+        let (batch, rate_limit) = svc.batch().oneshot(ips.clone()).await.unwrap();
+        assert_eq!(
+            batch
+                .iter()
+                .map(|x| x.as_success().unwrap().query)
+                .collect::<Vec<_>>(),
+            ips
+        );
+        assert!(rate_limit.is_some());
+        assert_eq!(svc.client.uris.lock().unwrap().len(), 3);
+
+        let (batch, _) = svc.batch().oneshot(vec![]).await.unwrap();
+        assert!(batch.is_empty());
+        assert_eq!(svc.client.uris.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_cache_layer() {
+        let (svc, mut handle) = mock::pair::<IpAddr, LookupResponse>();
+        let svc = CacheLayer::new(Duration::from_secs(60), 10).layer(svc);
+        let success: JsonResponseBodyJson = serde_json::from_slice(SUCCESS).unwrap();
+        let rate_limit = RateLimit {
+            remaining: Some(44),
+            seconds_until_reset: Some(60),
+        };
+
+        let res = tokio::spawn(svc.clone().oneshot(ip()));
+        let (req, send) = handle.next_request().await.unwrap();
+        assert_eq!(req, ip());
+        send.send_response((success.clone(), Some(rate_limit)));
+        let (json, x) = res.await.unwrap().unwrap();
+        assert!(json.is_success());
+        assert_eq!(x, Some(rate_limit));
+        assert_eq!(svc.len(), 1);
+
+        // Served from the cache, nothing reaches the mock.
+        let (json, rate_limit) = svc.clone().oneshot(ip()).await.unwrap();
+        assert!(json.is_success());
+        assert!(rate_limit.is_none());
+        assert!(
+            tokio::time::timeout(Duration::from_millis(10), handle.next_request())
+                .await
+                .is_err()
+        );
+
+        svc.clear();
+        assert!(svc.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cache_layer_skips_fails() {
+        let (svc, mut handle) = mock::pair::<IpAddr, LookupResponse>();
+        let svc = CacheLayer::new(Duration::from_secs(60), 10).layer(svc);
+        let fail: JsonResponseBodyJson = serde_json::from_str(
+            r#"{"status":"fail","message":"invalid query","query":"24.48.0.1"}"#,
+        )
+        .unwrap();
+
+        let res = tokio::spawn(svc.clone().oneshot(ip()));
+        handle
+            .next_request()
+            .await
+            .unwrap()
+            .1
+            .send_response((fail, None));
+        assert!(!res.await.unwrap().unwrap().0.is_success());
+        assert!(svc.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cache_layer_over_rate_limit() {
+        let lookup = LookupService::new(MockClient::default(), None);
+        let svc = ServiceBuilder::new()
+            .layer(CacheLayer::new(Duration::from_secs(3600), 100))
+            .layer(free_rate_limit_layer())
+            .service(lookup.clone());
+        let ips = (0..=FREE_REQUESTS_PER_MINUTE as u8)
+            .map(|x| IpAddr::from([24, 48, 0, x]))
+            .collect::<Vec<_>>();
+        let start = tokio::time::Instant::now();
+
+        for ip in &ips[..FREE_REQUESTS_PER_MINUTE as usize] {
+            svc.clone().oneshot(*ip).await.unwrap();
+        }
+        assert_eq!(lookup.client.uris.lock().unwrap().len(), 45);
+
+        // The window is used up, a miss waits for the next one.
+        let miss = tokio::spawn(svc.clone().oneshot(ips[45]));
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert!(!miss.is_finished());
+
+        // Hits do not.
+        let (json, rate_limit) = svc.clone().oneshot(ips[0]).await.unwrap();
+        assert!(json.is_success());
+        assert!(rate_limit.is_none());
+        assert!(start.elapsed() < Duration::from_secs(60));
+        assert_eq!(lookup.client.uris.lock().unwrap().len(), 45);
+
+        miss.await.unwrap().unwrap();
+        assert!(start.elapsed() >= Duration::from_secs(60));
+        assert_eq!(lookup.client.uris.lock().unwrap().len(), 46);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_timeout_layer() {
+        let (svc, _handle) = mock::pair::<IpAddr, LookupResponse>();
+        let svc = ServiceBuilder::new()
+            .layer(TimeoutLayer::new(Duration::from_secs(1)))
+            .layer(CacheLayer::new(Duration::from_secs(60), 10))
+            .service(svc);
+
+        let err = svc.oneshot(ip()).await.unwrap_err();
+        assert!(err.is::<Elapsed>());
+    }
+}
@@ -0,0 +1,277 @@
+//! Resolve the real client IP behind reverse proxies, independent of any web framework.
+//!
+//! Headers are only honoured when the peer is in the trusted CIDRs, and only the one header the
+//! proxies set, others may come from the client. The chain is walked from the right and the first
+//! untrusted hop is the client.
+
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+
+use http_api_client_endpoint::http::HeaderMap;
+use ipnet::IpNet;
+
+use crate::{endpoints::json::Json, types::api_key::ApiKey};
+
+pub const HEADER_FORWARDED: &str = "forwarded";
+pub const HEADER_X_FORWARDED_FOR: &str = "x-forwarded-for";
+pub const HEADER_X_REAL_IP: &str = "x-real-ip";
+
+/// The header the trusted proxies set.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum ForwardedHeader {
+    /// RFC 7239
+    Forwarded,
+    #[default]
+    XForwardedFor,
+    XRealIp,
+}
+
+impl ForwardedHeader {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Forwarded => HEADER_FORWARDED,
+            Self::XForwardedFor => HEADER_X_FORWARDED_FOR,
+            Self::XRealIp => HEADER_X_REAL_IP,
+        }
+    }
+}
+
+//
+#[derive(Debug, Clone, Default)]
+pub struct ClientIpResolver {
+    trusted_proxies: Arc<[IpNet]>,
+    header: ForwardedHeader,
+}
+
+impl ClientIpResolver {
+    /// Trusts nobody, i.e. always resolves to the peer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn trusted_proxies(mut self, proxies: impl IntoIterator<Item = IpNet>) -> Self {
+        self.trusted_proxies = proxies.into_iter().collect();
+        self
+    }
+
+    /// `X-Forwarded-For` by default.
+    pub fn header(mut self, header: ForwardedHeader) -> Self {
+        self.header = header;
+        self
+    }
+
+    pub fn is_trusted(&self, ip: &IpAddr) -> bool {
+        self.trusted_proxies.iter().any(|x| x.contains(ip))
+    }
+
+    pub fn resolve(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        if !self.is_trusted(&peer) {
+            return peer;
+        }
+
+        let hops = match self.header {
+            ForwardedHeader::Forwarded => forwarded_for(headers),
+            ForwardedHeader::XForwardedFor | ForwardedHeader::XRealIp => {
+                header_values(headers, self.header.name())
+                    .map(parse_hop)
+                    .collect()
+            }
+        };
+
+        let mut client = peer;
+        for hop in hops.into_iter().rev() {
+            match hop {
+                Some(ip) => {
+                    client = ip;
+                    if !self.is_trusted(&ip) {
+                        break;
+                    }
+                }
+                // "unknown" or obfuscated, nothing further left can be trusted.
+                None => break,
+            }
+        }
+        client
+    }
+
+    /// The resolved client as the `Json` query.
+    pub fn json(&self, peer: IpAddr, headers: &HeaderMap, key: Option<ApiKey>) -> Json {
+        Json::new(self.resolve(peer, headers).to_string(), key)
+    }
+}
+
+fn parse_hop(s: &str) -> Option<IpAddr> {
+    let s = s.trim().trim_matches('"');
+    s.parse::<IpAddr>()
+        .ok()
+        .or_else(|| s.parse::<SocketAddr>().ok().map(|x| x.ip()))
+        .or_else(|| s.strip_prefix('[')?.strip_suffix(']')?.parse().ok())
+}
+
+fn header_values<'a>(headers: &'a HeaderMap, name: &str) -> impl Iterator<Item = &'a str> {
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|x| x.to_str().ok())
+        .flat_map(|x| x.split(','))
+}
+
+/// https://www.rfc-editor.org/rfc/rfc7239, `None` for elements without `for=` too.
+fn forwarded_for(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    header_values(headers, HEADER_FORWARDED)
+        .map(|element| {
+            element.split(';').find_map(|pair| {
+                let (k, v) = pair.split_once('=')?;
+                k.trim()
+                    .eq_ignore_ascii_case("for")
+                    .then(|| parse_hop(v))
+                    .flatten()
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolver() -> ClientIpResolver {
+        ClientIpResolver::new()
+            .trusted_proxies(["10.0.0.0/8".parse().unwrap(), "fd00::/8".parse().unwrap()])
+    }
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (k, v) in pairs {
+            headers.append(*k, v.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_resolve() {
+        let resolver = resolver();
+        let proxy: IpAddr = "10.0.0.1".parse().unwrap();
+        let client: IpAddr = "24.48.0.1".parse().unwrap();
+
+        // Untrusted peers can not spoof.
+        let peer: IpAddr = "1.1.1.1".parse().unwrap();
+        assert_eq!(
+            resolver.resolve(peer, &headers(&[(HEADER_X_FORWARDED_FOR, "24.48.0.1")])),
+            peer
+        );
+        assert_eq!(
+            ClientIpResolver::new()
+                .resolve(proxy, &headers(&[(HEADER_X_FORWARDED_FOR, "24.48.0.1")])),
+            proxy
+        );
+
+        assert_eq!(
+            resolver.resolve(
+                proxy,
+                &headers(&[(HEADER_X_FORWARDED_FOR, "6.6.6.6, 24.48.0.1, 10.0.0.2")])
+            ),
+            client
+        );
+        assert_eq!(
+            resolver.resolve(
+                "fd00::1".parse().unwrap(),
+                &headers(&[
+                    (HEADER_X_FORWARDED_FOR, "6.6.6.6"),
+                    (HEADER_X_FORWARDED_FOR, "24.48.0.1:1234")
+                ])
+            ),
+            client
+        );
+        // Other headers may come from the client, through the proxy.
+        assert_eq!(
+            resolver.resolve(
+                proxy,
+                &headers(&[
+                    (HEADER_FORWARDED, "for=7.7.7.7"),
+                    (HEADER_X_REAL_IP, "7.7.7.7"),
+                    (HEADER_X_FORWARDED_FOR, "24.48.0.1")
+                ])
+            ),
+            client
+        );
+        assert_eq!(
+            resolver.resolve(proxy, &headers(&[(HEADER_FORWARDED, "for=7.7.7.7")])),
+            proxy
+        );
+
+        assert_eq!(resolver.resolve(proxy, &HeaderMap::new()), proxy);
+    }
+
+    #[test]
+    fn test_resolve_forwarded() {
+        let resolver = resolver().header(ForwardedHeader::Forwarded);
+        let proxy: IpAddr = "10.0.0.1".parse().unwrap();
+
+        assert_eq!(
+            resolver.resolve(
+                proxy,
+                &headers(&[
+                    (
+                        HEADER_FORWARDED,
+                        r#"for=6.6.6.6, for="[2001:db8:cafe::17]:4711";proto=https, For=10.0.0.2"#
+                    ),
+                    (HEADER_X_FORWARDED_FOR, "7.7.7.7")
+                ])
+            ),
+            "2001:db8:cafe::17".parse::<IpAddr>().unwrap()
+        );
+        assert_eq!(
+            resolver.resolve(proxy, &headers(&[(HEADER_FORWARDED, "for=unknown")])),
+            proxy
+        );
+        // Not added by the proxy, the walk stops there.
+        assert_eq!(
+            resolver.resolve(
+                proxy,
+                &headers(&[(HEADER_FORWARDED, "for=1.2.3.4, proto=https")])
+            ),
+            proxy
+        );
+        assert_eq!(
+            resolver.resolve(
+                proxy,
+                &headers(&[(HEADER_FORWARDED, "for=1.2.3.4, proto=https, for=10.0.0.2")])
+            ),
+            "10.0.0.2".parse::<IpAddr>().unwrap()
+        );
+        assert_eq!(
+            resolver.resolve(proxy, &headers(&[(HEADER_X_FORWARDED_FOR, "24.48.0.1")])),
+            proxy
+        );
+    }
+
+    #[test]
+    fn test_resolve_x_real_ip() {
+        let resolver = resolver().header(ForwardedHeader::XRealIp);
+        let proxy: IpAddr = "10.0.0.1".parse().unwrap();
+
+        assert_eq!(
+            resolver.resolve(
+                proxy,
+                &headers(&[
+                    (HEADER_X_REAL_IP, "24.48.0.1"),
+                    (HEADER_X_FORWARDED_FOR, "7.7.7.7")
+                ])
+            ),
+            "24.48.0.1".parse::<IpAddr>().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let json = resolver().json(
+            "10.0.0.1".parse().unwrap(),
+            &headers(&[(HEADER_X_FORWARDED_FOR, "24.48.0.1")]),
+            None,
+        );
+        assert_eq!(&*json.query, "24.48.0.1");
+    }
+}
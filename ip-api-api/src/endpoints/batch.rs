@@ -1,6 +1,6 @@
 //! https://members.ip-api.com/docs/batch
 
-use core::ops::Deref;
+use core::{fmt, marker::PhantomData, ops::Deref};
 
 use http_api_client_endpoint::{
     Body, Endpoint, MIME_APPLICATION_JSON, Request, Response,
@@ -9,17 +9,25 @@ use http_api_client_endpoint::{
         header::{ACCEPT, CONTENT_TYPE},
     },
 };
-use serde::Deserialize;
-use serde_json::{Map, Value};
+use serde::{
+    Deserialize, Serialize,
+    de::{self, IgnoredAny},
+};
+use serde_json::{Error as SerdeJsonError, Map, Value};
 use url::Url;
 
 use crate::{
     endpoints::{
-        URL_BASE, URL_BASE_PRO, common::EndpointError, helper::get_n_from_headers_by_key,
-        json::JsonResponseBodyJson,
+        URL_BASE, URL_BASE_PRO,
+        common::EndpointError,
+        helper::get_rate_limit_from_headers,
+        json::{FailReason, JsonResponseBodyFailJson, JsonResponseBodyJson},
+    },
+    objects::rate_limit::RateLimit,
+    types::{
+        api_key::{ApiKey, URL_QUERY_KEY},
+        lang::Lang,
     },
-    objects::rate_limit::{RESPONSE_HEADER_KEY_X_RL, RESPONSE_HEADER_KEY_X_TTL, RateLimit},
-    types::lang::Lang,
 };
 
 pub const MAX_QUERY: usize = 100;
@@ -28,7 +36,7 @@ pub const MAX_QUERY: usize = 100;
 #[derive(Debug, Clone)]
 pub struct Batch {
     pub queries: Vec<BatchQuery>,
-    pub key: Option<Box<str>>,
+    pub key: Option<ApiKey>,
     pub fields: Option<Box<str>>,
     pub lang: Option<Lang>,
 }
@@ -60,7 +68,7 @@ impl BatchQuery {
 }
 
 impl Batch {
-    pub fn new(queries: Vec<BatchQuery>, key: Option<Box<str>>) -> Self {
+    pub fn new(queries: Vec<BatchQuery>, key: Option<ApiKey>) -> Self {
         if queries.len() > MAX_QUERY {
             debug_assert!(false, "containing up to 100 IP addresses or objects");
         }
@@ -102,7 +110,8 @@ impl Endpoint for Batch {
         let mut url = Url::parse(url.as_str()).map_err(EndpointError::MakeRequestUrlFailed)?;
 
         if let Some(key) = &self.key {
-            url.query_pairs_mut().append_pair("key", key);
+            url.query_pairs_mut()
+                .append_pair(URL_QUERY_KEY, key.expose());
         }
         if let Some(fields) = &self.fields {
             url.query_pairs_mut().append_pair("fields", fields);
@@ -151,32 +160,93 @@ impl Endpoint for Batch {
         response: Response<Body>,
     ) -> Result<Self::ParseResponseOutput, Self::ParseResponseError> {
         let json = serde_json::from_slice(response.body())
-            .map_err(EndpointError::DeResponseBodyJsonFailed)?;
+            .map_err(|err| self.map_de_response_body_json_err(response.body(), err))?;
 
         let rate_limit = if self.key.is_some() {
             None
         } else {
-            Some(RateLimit {
-                remaining: get_n_from_headers_by_key(response.headers(), RESPONSE_HEADER_KEY_X_RL)
-                    .ok(),
-                seconds_until_reset: get_n_from_headers_by_key(
-                    response.headers(),
-                    RESPONSE_HEADER_KEY_X_TTL,
-                )
-                .ok(),
-            })
+            Some(get_rate_limit_from_headers(response.headers()))
         };
 
         Ok((json, rate_limit))
     }
 }
 
+impl Batch {
+    pub fn lenient(self) -> LenientBatch {
+        LenientBatch(self)
+    }
+
+    fn map_de_response_body_json_err(&self, body: &[u8], err: SerdeJsonError) -> EndpointError {
+        // With an invalid key, the body is a single fail object instead of an array.
+        if self.key.is_some()
+            && let Ok(fail_json) = serde_json::from_slice::<JsonResponseBodyFailJson>(body)
+            && fail_json.reason() == FailReason::KeyInvalid
+        {
+            return EndpointError::KeyInvalid(fail_json.message);
+        }
+        EndpointError::DeResponseBodyJsonFailed(err)
+    }
+
+    /// Like `parse_response`, but the elements are parsed while iterating.
+    pub fn parse_response_iter<'a>(
+        &self,
+        response: &'a Response<Body>,
+    ) -> (BatchResponseBodyJsonIter<'a>, Option<RateLimit>) {
+        let rate_limit = if self.key.is_some() {
+            None
+        } else {
+            Some(get_rate_limit_from_headers(response.headers()))
+        };
+
+        (BatchResponseBodyJsonIter::new(response.body()), rate_limit)
+    }
+}
+
+//
+/// `Batch` whose response elements are deserialized one by one, see `BatchResponseBodyLenientJson`.
+#[derive(Debug, Clone)]
+pub struct LenientBatch(pub Batch);
+
+impl Endpoint for LenientBatch {
+    type RenderRequestError = EndpointError;
+
+    type ParseResponseOutput = (BatchResponseBodyLenientJson, Option<RateLimit>);
+    type ParseResponseError = EndpointError;
+
+    fn render_request(&self) -> Result<Request<Body>, Self::RenderRequestError> {
+        self.0.render_request()
+    }
+
+    fn parse_response(
+        &self,
+        response: Response<Body>,
+    ) -> Result<Self::ParseResponseOutput, Self::ParseResponseError> {
+        let (iter, rate_limit) = self.0.parse_response_iter(&response);
+        let json = BatchResponseBodyLenientJson::try_collect(iter)
+            .map_err(|err| self.0.map_de_response_body_json_err(response.body(), err))?;
+
+        Ok((json, rate_limit))
+    }
+}
+
 //
 //
 //
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct BatchResponseBodyJson(pub Vec<JsonResponseBodyJson>);
 
+impl BatchResponseBodyJson {
+    /// Deserialize, failing on any field not known to this crate, e.g. for contract tests.
+    pub fn from_slice_strict(slice: &[u8]) -> Result<Self, SerdeJsonError> {
+        let json: Self = serde_json::from_slice(slice)?;
+        for x in json.iter() {
+            x.deny_extra()?;
+        }
+        Ok(json)
+    }
+}
+
 impl Deref for BatchResponseBodyJson {
     type Target = Vec<JsonResponseBodyJson>;
 
@@ -185,12 +255,199 @@ impl Deref for BatchResponseBodyJson {
     }
 }
 
+//
+/// Iterate the elements of a batch response body, each one is deserialized on its own,
+/// so an element with an unexpected shape does not stop the iteration.
+///
+/// `T` may also be `JsonResponseBodyJsonRef<'a>`.
+#[derive(Debug)]
+pub struct BatchResponseBodyJsonIter<'a, T = JsonResponseBodyJson> {
+    slice: &'a [u8],
+    offset: usize,
+    index: usize,
+    state: BatchResponseBodyJsonIterState,
+    phantom: PhantomData<T>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum BatchResponseBodyJsonIterState {
+    Start,
+    Element,
+    Done,
+}
+
+impl<'a, T> BatchResponseBodyJsonIter<'a, T> {
+    pub fn new(slice: &'a [u8]) -> Self {
+        Self {
+            slice,
+            offset: 0,
+            index: 0,
+            state: BatchResponseBodyJsonIterState::Start,
+            phantom: PhantomData,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\n' | b'\r' | b'\t') = self.slice.get(self.offset) {
+            self.offset += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.slice.get(self.offset).copied()
+    }
+
+    /// At the closing `]`, only whitespace may follow, as for `serde_json::from_slice`.
+    fn end(&mut self) -> Option<Result<T, BatchResponseBodyJsonIterError>> {
+        self.offset += 1;
+        if self.peek().is_some() {
+            return Some(Err(self.syntax_error("trailing characters")));
+        }
+        self.state = BatchResponseBodyJsonIterState::Done;
+        None
+    }
+
+    fn syntax_error(&mut self, msg: &str) -> BatchResponseBodyJsonIterError {
+        self.state = BatchResponseBodyJsonIterState::Done;
+        BatchResponseBodyJsonIterError::Syntax(de::Error::custom(format!(
+            "{} at byte {}",
+            msg, self.offset
+        )))
+    }
+}
+
+impl<'a, T> Iterator for BatchResponseBodyJsonIter<'a, T>
+where
+    T: Deserialize<'a>,
+{
+    type Item = Result<T, BatchResponseBodyJsonIterError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.state {
+            BatchResponseBodyJsonIterState::Done => return None,
+            BatchResponseBodyJsonIterState::Start => {
+                if self.peek() != Some(b'[') {
+                    return Some(Err(self.syntax_error("expected `[`")));
+                }
+                self.offset += 1;
+                if self.peek() == Some(b']') {
+                    return self.end();
+                }
+                self.state = BatchResponseBodyJsonIterState::Element;
+            }
+            BatchResponseBodyJsonIterState::Element => match self.peek() {
+                Some(b',') => self.offset += 1,
+                Some(b']') => return self.end(),
+                _ => return Some(Err(self.syntax_error("expected `,` or `]`"))),
+            },
+        }
+
+        // Find the element span first, then deserialize only that span.
+        self.skip_whitespace();
+        let start = self.offset;
+        let mut stream =
+            serde_json::Deserializer::from_slice(&self.slice[start..]).into_iter::<IgnoredAny>();
+        match stream.next() {
+            Some(Ok(_)) => {}
+            Some(Err(err)) => {
+                self.state = BatchResponseBodyJsonIterState::Done;
+                return Some(Err(BatchResponseBodyJsonIterError::Syntax(err)));
+            }
+            None => return Some(Err(self.syntax_error("expected value"))),
+        }
+        self.offset = start + stream.byte_offset();
+
+        let index = self.index;
+        self.index += 1;
+
+        Some(
+            serde_json::from_slice(&self.slice[start..self.offset]).map_err(|error| {
+                BatchResponseBodyJsonIterError::Element(ElementError { index, error })
+            }),
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum BatchResponseBodyJsonIterError {
+    /// The body is not a json array, the iteration stops.
+    Syntax(SerdeJsonError),
+    /// The element could not be deserialized, the iteration continues.
+    Element(ElementError),
+}
+
+impl fmt::Display for BatchResponseBodyJsonIterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for BatchResponseBodyJsonIterError {}
+
+#[derive(Debug)]
+pub struct ElementError {
+    pub index: usize,
+    pub error: SerdeJsonError,
+}
+
+impl fmt::Display for ElementError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "element [{}] invalid, {}", self.index, self.error)
+    }
+}
+
+impl std::error::Error for ElementError {}
+
+//
+/// Unlike `BatchResponseBodyJson`, an element with an unexpected shape
+/// does not fail the whole batch.
+#[derive(Debug)]
+pub struct BatchResponseBodyLenientJson(pub Vec<Result<JsonResponseBodyJson, ElementError>>);
+
+impl Deref for BatchResponseBodyLenientJson {
+    type Target = Vec<Result<JsonResponseBodyJson, ElementError>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl BatchResponseBodyLenientJson {
+    /// Fails only if the body is not a json array.
+    pub fn from_slice(slice: &[u8]) -> Result<Self, SerdeJsonError> {
+        Self::try_collect(BatchResponseBodyJsonIter::new(slice))
+    }
+
+    fn try_collect(iter: BatchResponseBodyJsonIter<'_>) -> Result<Self, SerdeJsonError> {
+        let mut elements = vec![];
+        for x in iter {
+            match x {
+                Ok(json) => elements.push(Ok(json)),
+                Err(BatchResponseBodyJsonIterError::Element(err)) => elements.push(Err(err)),
+                Err(BatchResponseBodyJsonIterError::Syntax(err)) => return Err(err),
+            }
+        }
+        Ok(Self(elements))
+    }
+
+    pub fn successes(&self) -> impl Iterator<Item = &JsonResponseBodyJson> {
+        self.0.iter().filter_map(|x| x.as_ref().ok())
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &ElementError> {
+        self.0.iter().filter_map(|x| x.as_ref().err())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     use serde_json::json;
 
+    use crate::endpoints::borrowed::JsonResponseBodyJsonRef;
+
     #[test]
     fn test_render_request() {
         let batch = Batch::new(vec![BatchQuery::new("24.48.0.1")], None);
@@ -210,6 +467,7 @@ mod tests {
         );
         let req = batch.render_request().unwrap();
         assert_eq!(req.uri(), "https://pro.ip-api.com/batch?key=foo");
+        assert!(!format!("{:?}", batch).contains("foo"));
         assert_eq!(
             req.body(),
             json! {
@@ -287,4 +545,132 @@ mod tests {
             ret => panic!("{:?}", ret),
         }
     }
+
+    #[test]
+    fn test_parse_response_with_key_invalid() {
+        let batch = Batch::new(vec![BatchQuery::new("24.48.0.1")], Some("foo".into()));
+        let res = Response::builder()
+            .status(403)
+            .body(include_bytes!("../../tests/response_body_json_files/json_err_3.json").to_vec())
+            .unwrap();
+        match batch.parse_response(res) {
+            Err(EndpointError::KeyInvalid(_)) => {}
+            ret => panic!("{:?}", ret),
+        }
+    }
+
+    #[test]
+    fn test_response_body_json_iter() {
+        let body = include_bytes!("../../tests/response_body_json_files/batch_simple.json");
+        let queries = BatchResponseBodyJsonIter::<JsonResponseBodyJson>::new(body)
+            .map(|x| x.unwrap().as_success().unwrap().query.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(queries, vec!["208.80.152.201", "8.8.8.8", "24.48.0.1"]);
+
+        let body = br#" [ {"query":"8.8.8.8"}, {"status":"foo"} ,{"status":"fail","message":"invalid query","query":"2"} ] "#;
+        let mut iter = BatchResponseBodyJsonIter::<JsonResponseBodyJson>::new(body);
+        assert!(iter.next().unwrap().unwrap().is_success());
+        match iter.next() {
+            Some(Err(BatchResponseBodyJsonIterError::Element(err))) => {
+                assert_eq!(err.index, 1);
+                assert!(err.error.to_string().contains("status [foo] mismatch"));
+            }
+            x => panic!("{:?}", x),
+        }
+        assert!(!iter.next().unwrap().unwrap().is_success());
+        assert!(iter.next().is_none());
+        assert!(iter.next().is_none());
+
+        let mut iter = BatchResponseBodyJsonIter::<JsonResponseBodyJsonRef>::new(b"[] \n");
+        assert!(iter.next().is_none());
+
+        for body in [
+            &b"{}"[..],
+            b"[{}",
+            b"[{} {}]",
+            b"[{},]",
+            b"[{\"query\":",
+            b"[{}]garbage",
+            b"[] []",
+        ] {
+            let items =
+                BatchResponseBodyJsonIter::<JsonResponseBodyJson>::new(body).collect::<Vec<_>>();
+            match items.last() {
+                Some(Err(BatchResponseBodyJsonIterError::Syntax(_))) => {}
+                x => panic!("{:?} {:?}", String::from_utf8_lossy(body), x),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_response_lenient() {
+        let batch = Batch::new(
+            vec![
+                BatchQuery::new("8.8.8.8"),
+                BatchQuery::new("1.1.1.1"),
+                BatchQuery::new("2"),
+            ],
+            None,
+        )
+        .lenient();
+        let res = Response::builder()
+            .header("X-Rl", "14")
+            .body(
+                br#"[{"query":"8.8.8.8"},{"status":"foo","query":"1.1.1.1"},{"status":"fail","message":"invalid query","query":"2"}]"#
+                    .to_vec(),
+            )
+            .unwrap();
+        let (json, rate_limit) = batch.parse_response(res).unwrap();
+        assert_eq!(rate_limit.unwrap().remaining, Some(14));
+        assert_eq!(json.len(), 3);
+        assert_eq!(json.successes().count(), 2);
+        assert_eq!(json.errors().map(|x| x.index).collect::<Vec<_>>(), vec![1]);
+
+        let res = Response::builder().body(b"[{}".to_vec()).unwrap();
+        match batch.parse_response(res) {
+            Err(EndpointError::DeResponseBodyJsonFailed(_)) => {}
+            ret => panic!("{:?}", ret),
+        }
+
+        let batch = Batch::new(vec![BatchQuery::new("8.8.8.8")], Some("foo".into())).lenient();
+        let res = Response::builder()
+            .status(403)
+            .body(include_bytes!("../../tests/response_body_json_files/json_err_3.json").to_vec())
+            .unwrap();
+        match batch.parse_response(res) {
+            Err(EndpointError::KeyInvalid(_)) => {}
+            ret => panic!("{:?}", ret),
+        }
+    }
+
+    #[test]
+    fn test_ser_response_body_json() {
+        let s =
+            include_str!("../../tests/response_body_json_files/batch_simple_with_part_err.json");
+        let json = serde_json::from_str::<BatchResponseBodyJson>(s).unwrap();
+        let value = serde_json::to_value(&json).unwrap();
+        assert_eq!(value[0]["status"], "success");
+        assert_eq!(value[0]["countryCode"], "US");
+        assert_eq!(value[0]["timezone"], "America/Chicago");
+        assert_eq!(
+            value[1],
+            json!({"status": "fail", "message": "invalid query", "query": "2"})
+        );
+    }
+
+    #[test]
+    fn test_de_response_body_json_strict() {
+        for s in [
+            &include_bytes!("../../tests/response_body_json_files/batch_simple.json")[..],
+            include_bytes!("../../tests/response_body_json_files/batch_simple_with_part_err.json"),
+        ] {
+            BatchResponseBodyJson::from_slice_strict(s).unwrap();
+        }
+
+        let err = BatchResponseBodyJson::from_slice_strict(
+            br#"[{"query":"8.8.8.8"},{"query":"1.1.1.1","foo":1}]"#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("unknown field `foo`"));
+    }
 }
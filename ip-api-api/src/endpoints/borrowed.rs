@@ -0,0 +1,464 @@
+//! Response types borrowing their strings from the response body.
+
+use core::{fmt, ops::Deref};
+use std::{borrow::Cow, net::IpAddr};
+
+use chrono::{DateTime, FixedOffset};
+use chrono_tz::Tz;
+use continent_code::ContinentCode;
+use country_code::CountryCode;
+use serde::{Deserialize, Deserializer, de};
+use serde_json::{Map, Value};
+
+use crate::{
+    endpoints::{
+        batch::BatchResponseBodyJson,
+        json::{
+            JsonResponseBodyFailJson, JsonResponseBodyJson, JsonResponseBodySuccessJson,
+            PresentFields,
+        },
+    },
+    types::{calling_code::CallingCode, currency::Currency},
+};
+
+//
+#[derive(Debug, Clone)]
+pub enum JsonResponseBodyJsonRef<'a> {
+    Success(Box<JsonResponseBodySuccessJsonRef<'a>>),
+    Fail(JsonResponseBodyFailJsonRef<'a>),
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for JsonResponseBodyJsonRef<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let raw = JsonResponseBodyRawJson::deserialize(deserializer)?;
+        raw.try_into().map_err(de::Error::custom)
+    }
+}
+
+impl JsonResponseBodyJsonRef<'_> {
+    pub fn is_success(&self) -> bool {
+        matches!(self, Self::Success(_))
+    }
+
+    pub fn into_owned(self) -> JsonResponseBodyJson {
+        self.into()
+    }
+}
+
+impl From<JsonResponseBodyJsonRef<'_>> for JsonResponseBodyJson {
+    fn from(x: JsonResponseBodyJsonRef<'_>) -> Self {
+        match x {
+            JsonResponseBodyJsonRef::Success(x) => Self::Success(Box::new((*x).into())),
+            JsonResponseBodyJsonRef::Fail(x) => Self::Fail(x.into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct JsonResponseBodySuccessJsonRef<'a> {
+    pub query: IpAddr,
+    //
+    pub continent: Cow<'a, str>,
+    pub continent_code: ContinentCode,
+    //
+    pub country: Cow<'a, str>,
+    pub country_code: CountryCode,
+    pub country_code3: Cow<'a, str>,
+    //
+    pub region: Cow<'a, str>,
+    pub region_name: Cow<'a, str>,
+    //
+    pub city: Cow<'a, str>,
+    pub district: Cow<'a, str>,
+    //
+    pub zip: Cow<'a, str>,
+    //
+    pub lat: f64,
+    pub lon: f64,
+    //
+    pub timezone: Tz,
+    pub offset: isize,
+    pub current_time: DateTime<FixedOffset>,
+    //
+    pub currency: Currency,
+    //
+    pub calling_code: CallingCode,
+    //
+    pub isp: Cow<'a, str>,
+    pub org: Cow<'a, str>,
+    pub r#as: Cow<'a, str>,
+    pub asname: Cow<'a, str>,
+    pub reverse: Cow<'a, str>,
+    //
+    pub mobile: bool,
+    pub proxy: bool,
+    pub hosting: bool,
+    //
+    pub present: PresentFields,
+    pub extra: Map<String, Value>,
+}
+
+impl From<JsonResponseBodySuccessJsonRef<'_>> for JsonResponseBodySuccessJson {
+    fn from(x: JsonResponseBodySuccessJsonRef<'_>) -> Self {
+        Self {
+            query: x.query,
+            continent: x.continent.into(),
+            continent_code: x.continent_code,
+            country: x.country.into(),
+            country_code: x.country_code,
+            country_code3: x.country_code3.into(),
+            region: x.region.into(),
+            region_name: x.region_name.into(),
+            city: x.city.into(),
+            district: x.district.into(),
+            zip: x.zip.into(),
+            lat: x.lat,
+            lon: x.lon,
+            timezone: x.timezone,
+            offset: x.offset,
+            current_time: x.current_time,
+            currency: x.currency,
+            calling_code: x.calling_code,
+            isp: x.isp.into(),
+            org: x.org.into(),
+            r#as: x.r#as.into(),
+            asname: x.asname.into(),
+            reverse: x.reverse.into(),
+            mobile: x.mobile,
+            proxy: x.proxy,
+            hosting: x.hosting,
+            present: x.present,
+            extra: x.extra,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct JsonResponseBodyFailJsonRef<'a> {
+    pub query: Cow<'a, str>,
+    //
+    pub message: Cow<'a, str>,
+    //
+    pub extra: Map<String, Value>,
+}
+
+impl From<JsonResponseBodyFailJsonRef<'_>> for JsonResponseBodyFailJson {
+    fn from(x: JsonResponseBodyFailJsonRef<'_>) -> Self {
+        Self {
+            query: x.query.into(),
+            message: x.message.into(),
+            extra: x.extra,
+        }
+    }
+}
+
+//
+#[derive(Deserialize, Debug, Clone)]
+pub struct BatchResponseBodyJsonRef<'a>(#[serde(borrow)] pub Vec<JsonResponseBodyJsonRef<'a>>);
+
+impl<'a> Deref for BatchResponseBodyJsonRef<'a> {
+    type Target = Vec<JsonResponseBodyJsonRef<'a>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl BatchResponseBodyJsonRef<'_> {
+    pub fn into_owned(self) -> BatchResponseBodyJson {
+        BatchResponseBodyJson(self.0.into_iter().map(Into::into).collect())
+    }
+}
+
+//
+/// Both shapes in one struct, so the body is visited once and then dispatched
+/// by `status` (or by the presence of `message` when `status` is not in `fields`).
+#[derive(Deserialize)]
+struct JsonResponseBodyRawJson<'a> {
+    #[serde(default)]
+    status: Option<JsonResponseBodyStatus>,
+    #[serde(borrow, default, deserialize_with = "de_option_cow_str")]
+    message: Option<Cow<'a, str>>,
+    #[serde(borrow, default, deserialize_with = "de_option_cow_str")]
+    query: Option<Cow<'a, str>>,
+    //
+    #[serde(borrow, default, deserialize_with = "de_option_cow_str")]
+    continent: Option<Cow<'a, str>>,
+    #[serde(default, rename = "continentCode")]
+    continent_code: Option<ContinentCode>,
+    //
+    #[serde(borrow, default, deserialize_with = "de_option_cow_str")]
+    country: Option<Cow<'a, str>>,
+    #[serde(default, rename = "countryCode")]
+    country_code: Option<CountryCode>,
+    #[serde(
+        borrow,
+        default,
+        rename = "countryCode3",
+        deserialize_with = "de_option_cow_str"
+    )]
+    country_code3: Option<Cow<'a, str>>,
+    //
+    #[serde(borrow, default, deserialize_with = "de_option_cow_str")]
+    region: Option<Cow<'a, str>>,
+    #[serde(
+        borrow,
+        default,
+        rename = "regionName",
+        deserialize_with = "de_option_cow_str"
+    )]
+    region_name: Option<Cow<'a, str>>,
+    //
+    #[serde(borrow, default, deserialize_with = "de_option_cow_str")]
+    city: Option<Cow<'a, str>>,
+    #[serde(borrow, default, deserialize_with = "de_option_cow_str")]
+    district: Option<Cow<'a, str>>,
+    //
+    #[serde(borrow, default, deserialize_with = "de_option_cow_str")]
+    zip: Option<Cow<'a, str>>,
+    //
+    #[serde(default)]
+    lat: Option<f64>,
+    #[serde(default)]
+    lon: Option<f64>,
+    //
+    #[serde(default, deserialize_with = "de_option_tz")]
+    timezone: Option<Tz>,
+    #[serde(default)]
+    offset: Option<isize>,
+    #[serde(default, rename = "currentTime")]
+    current_time: Option<DateTime<FixedOffset>>,
+    //
+    #[serde(default)]
+    currency: Option<Currency>,
+    //
+    #[serde(default, rename = "callingCode")]
+    calling_code: Option<CallingCode>,
+    //
+    #[serde(borrow, default, deserialize_with = "de_option_cow_str")]
+    isp: Option<Cow<'a, str>>,
+    #[serde(borrow, default, deserialize_with = "de_option_cow_str")]
+    org: Option<Cow<'a, str>>,
+    #[serde(borrow, default, deserialize_with = "de_option_cow_str")]
+    r#as: Option<Cow<'a, str>>,
+    #[serde(borrow, default, deserialize_with = "de_option_cow_str")]
+    asname: Option<Cow<'a, str>>,
+    #[serde(borrow, default, deserialize_with = "de_option_cow_str")]
+    reverse: Option<Cow<'a, str>>,
+    //
+    #[serde(default)]
+    mobile: Option<bool>,
+    #[serde(default)]
+    proxy: Option<bool>,
+    #[serde(default)]
+    hosting: Option<bool>,
+    //
+    #[serde(flatten)]
+    extra: Map<String, Value>,
+}
+
+fn default_date_time_fixed_offset() -> DateTime<FixedOffset> {
+    serde_field_default::chrono::default_date_time_utc().fixed_offset()
+}
+
+fn de_option_tz<'de, D>(deserializer: D) -> Result<Option<Tz>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Wrapper(#[serde(deserialize_with = "serde_field_with::from_str")] Tz);
+
+    Option::<Wrapper>::deserialize(deserializer).map(|x| x.map(|Wrapper(tz)| tz))
+}
+
+/// `Option<Cow<str>>` would always be owned without this.
+fn de_option_cow_str<'de: 'a, 'a, D>(deserializer: D) -> Result<Option<Cow<'a, str>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Wrapper<'a>(#[serde(borrow)] Cow<'a, str>);
+
+    Option::<Wrapper>::deserialize(deserializer).map(|x| x.map(|Wrapper(s)| s))
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum JsonResponseBodyStatus {
+    Success,
+    Fail,
+}
+
+impl<'de> Deserialize<'de> for JsonResponseBodyStatus {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct StatusVisitor;
+
+        impl de::Visitor<'_> for StatusVisitor {
+            type Value = JsonResponseBodyStatus;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("success or fail")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                match v {
+                    "success" => Ok(JsonResponseBodyStatus::Success),
+                    "fail" => Ok(JsonResponseBodyStatus::Fail),
+                    s => Err(de::Error::custom(format!("status [{}] mismatch", s))),
+                }
+            }
+        }
+
+        deserializer.deserialize_str(StatusVisitor)
+    }
+}
+
+impl<'a> TryFrom<JsonResponseBodyRawJson<'a>> for JsonResponseBodyJsonRef<'a> {
+    type Error = String;
+
+    fn try_from(raw: JsonResponseBodyRawJson<'a>) -> Result<Self, Self::Error> {
+        let status = match raw.status {
+            Some(status) => status,
+            None if raw.message.is_some() => JsonResponseBodyStatus::Fail,
+            None => JsonResponseBodyStatus::Success,
+        };
+
+        match status {
+            JsonResponseBodyStatus::Fail => Ok(Self::Fail(JsonResponseBodyFailJsonRef {
+                query: raw.query.unwrap_or_default(),
+                message: raw.message.ok_or("missing field `message`")?,
+                extra: raw.extra,
+            })),
+            JsonResponseBodyStatus::Success => {
+                let mut present = PresentFields::NONE;
+                let query = match raw.query {
+                    Some(query) => {
+                        present.insert("query");
+                        query
+                            .parse()
+                            .map_err(|err| format!("query [{}] invalid, {}", query, err))?
+                    }
+                    None => serde_field_default::default_ip_addr(),
+                };
+                macro_rules! field {
+                    ($name:literal, $value:expr) => {
+                        field!($name, $value, Default::default())
+                    };
+                    ($name:literal, $value:expr, $default:expr) => {
+                        match $value {
+                            Some(x) => {
+                                present.insert($name);
+                                x
+                            }
+                            None => $default,
+                        }
+                    };
+                }
+
+                Ok(Self::Success(
+                    JsonResponseBodySuccessJsonRef {
+                        query,
+                        continent: field!("continent", raw.continent),
+                        continent_code: field!("continentCode", raw.continent_code),
+                        country: field!("country", raw.country),
+                        country_code: field!("countryCode", raw.country_code),
+                        country_code3: field!("countryCode3", raw.country_code3),
+                        region: field!("region", raw.region),
+                        region_name: field!("regionName", raw.region_name),
+                        city: field!("city", raw.city),
+                        district: field!("district", raw.district),
+                        zip: field!("zip", raw.zip),
+                        lat: field!("lat", raw.lat),
+                        lon: field!("lon", raw.lon),
+                        timezone: field!(
+                            "timezone",
+                            raw.timezone,
+                            serde_field_default::chrono_tz::default_tz()
+                        ),
+                        offset: field!("offset", raw.offset),
+                        current_time: field!(
+                            "currentTime",
+                            raw.current_time,
+                            default_date_time_fixed_offset()
+                        ),
+                        currency: field!("currency", raw.currency),
+                        calling_code: field!("callingCode", raw.calling_code),
+                        isp: field!("isp", raw.isp),
+                        org: field!("org", raw.org),
+                        r#as: field!("as", raw.r#as),
+                        asname: field!("asname", raw.asname),
+                        reverse: field!("reverse", raw.reverse),
+                        mobile: field!("mobile", raw.mobile),
+                        proxy: field!("proxy", raw.proxy),
+                        hosting: field!("hosting", raw.hosting),
+                        present,
+                        extra: raw.extra,
+                    }
+                    .into(),
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_de_response_body_json() {
+        match serde_json::from_str::<JsonResponseBodyJsonRef>(include_str!(
+            "../../tests/response_body_json_files/json_full_fields.json"
+        )) {
+            Ok(JsonResponseBodyJsonRef::Success(ok_json)) => {
+                assert!(matches!(ok_json.city, Cow::Borrowed("Montreal")));
+                assert!(matches!(
+                    ok_json.r#as,
+                    Cow::Borrowed("AS5769 Videotron Telecom Ltee")
+                ));
+
+                let ok_json = JsonResponseBodySuccessJson::from(*ok_json);
+                assert_eq!(ok_json.query.to_string(), "24.48.0.1");
+                assert_eq!(ok_json.city, "Montreal".into());
+                assert_eq!(ok_json.country_code, CountryCode::CA);
+            }
+            ret => panic!("{:?}", ret),
+        }
+
+        match serde_json::from_str::<JsonResponseBodyJsonRef>(
+            r#"{"status":"fail","message":"invalid \"query\"","query":"24"}"#,
+        ) {
+            Ok(JsonResponseBodyJsonRef::Fail(err_json)) => {
+                assert!(matches!(err_json.query, Cow::Borrowed("24")));
+                assert!(matches!(err_json.message, Cow::Owned(_)));
+                assert_eq!(err_json.message, r#"invalid "query""#);
+            }
+            ret => panic!("{:?}", ret),
+        }
+    }
+
+    #[test]
+    fn test_de_batch_response_body_json() {
+        let json = serde_json::from_str::<BatchResponseBodyJsonRef>(include_str!(
+            "../../tests/response_body_json_files/batch_simple_with_part_err.json"
+        ))
+        .unwrap();
+        assert_eq!(json.len(), 2);
+        assert!(json[0].is_success());
+        assert!(!json[1].is_success());
+
+        let json = json.into_owned();
+        match &json[1] {
+            JsonResponseBodyJson::Fail(err_json) => assert_eq!(err_json.query, "2".into()),
+            x => panic!("{:?}", x),
+        }
+    }
+}
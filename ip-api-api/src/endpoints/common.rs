@@ -11,6 +11,7 @@ pub enum EndpointError {
     MakeRequestFailed(HttpError),
     SerRequestBodyJsonFailed(SerdeJsonError),
     DeResponseBodyJsonFailed(SerdeJsonError),
+    KeyInvalid(Box<str>),
 }
 
 impl fmt::Display for EndpointError {
@@ -0,0 +1,89 @@
+//! https://ip-api.com/docs/api:json#fieldsTable
+//!
+//! `fields` is either comma separated names or a numeric mask of them.
+
+/// Returned when `fields` is not set.
+pub const DEFAULT_FIELDS: &[&str] = &[
+    "status",
+    "message",
+    "country",
+    "countryCode",
+    "region",
+    "regionName",
+    "city",
+    "zip",
+    "lat",
+    "lon",
+    "timezone",
+    "isp",
+    "org",
+    "as",
+    "query",
+];
+
+pub const FIELD_MASKS: &[(&str, u32)] = &[
+    ("status", 16384),
+    ("message", 32768),
+    ("continent", 1048576),
+    ("continentCode", 2097152),
+    ("country", 1),
+    ("countryCode", 2),
+    ("region", 4),
+    ("regionName", 8),
+    ("city", 16),
+    ("district", 524288),
+    ("zip", 32),
+    ("lat", 64),
+    ("lon", 128),
+    ("timezone", 256),
+    ("offset", 33554432),
+    ("currency", 8388608),
+    ("isp", 512),
+    ("org", 1024),
+    ("as", 2048),
+    ("asname", 4194304),
+    ("reverse", 4096),
+    ("mobile", 65536),
+    ("proxy", 131072),
+    ("hosting", 16777216),
+    ("query", 8192),
+];
+
+/// Names in `fields`, a numeric mask is expanded in `FIELD_MASKS` order.
+pub fn field_names(fields: &str) -> Vec<Box<str>> {
+    match fields.trim().parse::<u32>() {
+        Ok(mask) => FIELD_MASKS
+            .iter()
+            .filter(|(_, bit)| mask & bit != 0)
+            .map(|(name, _)| (*name).into())
+            .collect(),
+        Err(_) => fields
+            .split(',')
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .map(Into::into)
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field_names() {
+        assert_eq!(
+            &*field_names("status, query,"),
+            [Box::from("status"), "query".into()]
+        );
+        assert_eq!(field_names("66846719").len(), FIELD_MASKS.len());
+        assert_eq!(
+            &*field_names("24578"),
+            [Box::from("status"), "countryCode".into(), "query".into()]
+        );
+        assert_eq!(
+            FIELD_MASKS.iter().map(|(_, bit)| bit).sum::<u32>(),
+            66846719
+        );
+    }
+}
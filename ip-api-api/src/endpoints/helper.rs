@@ -1,5 +1,14 @@
 use http_api_client_endpoint::http::{HeaderMap, HeaderValue};
 
+use crate::objects::rate_limit::{RESPONSE_HEADER_KEY_X_RL, RESPONSE_HEADER_KEY_X_TTL, RateLimit};
+
+pub(super) fn get_rate_limit_from_headers(headers: &HeaderMap<HeaderValue>) -> RateLimit {
+    RateLimit {
+        remaining: get_n_from_headers_by_key(headers, RESPONSE_HEADER_KEY_X_RL).ok(),
+        seconds_until_reset: get_n_from_headers_by_key(headers, RESPONSE_HEADER_KEY_X_TTL).ok(),
+    }
+}
+
 pub(super) fn get_n_from_headers_by_key(
     headers: &HeaderMap<HeaderValue>,
     key: &str,
@@ -1,37 +1,49 @@
 //! https://members.ip-api.com/docs/json
 //! https://ip-api.com/docs/api:json
 
+use core::fmt;
 use std::net::IpAddr;
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, FixedOffset, Offset as _, TimeZone as _, Utc};
 use chrono_tz::Tz;
 use continent_code::ContinentCode;
-use country_code::CountryCode;
+use country_code::{CountryCode, iso3166_2::SubdivisionCode};
 use http_api_client_endpoint::{
     Body, Endpoint, MIME_APPLICATION_JSON, Request, Response,
     http::{Method, header::ACCEPT},
 };
-use serde::{Deserialize, Deserializer, de};
-use serde_json::{Map, Value};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de, ser::SerializeMap as _};
+use serde_json::{Error as SerdeJsonError, Map, Value};
 use url::Url;
 
 use crate::{
-    endpoints::{URL_BASE, URL_BASE_PRO, common::EndpointError, helper::get_n_from_headers_by_key},
-    objects::rate_limit::{RESPONSE_HEADER_KEY_X_RL, RESPONSE_HEADER_KEY_X_TTL, RateLimit},
-    types::lang::Lang,
+    endpoints::{
+        URL_BASE, URL_BASE_PRO, borrowed::JsonResponseBodyJsonRef, common::EndpointError,
+        helper::get_rate_limit_from_headers,
+    },
+    objects::rate_limit::RateLimit,
+    types::{
+        api_key::{ApiKey, URL_QUERY_KEY},
+        asn::{Asn, AutonomousSystem},
+        calling_code::CallingCode,
+        currency::Currency,
+        geo_point::GeoPoint,
+        lang::Lang,
+        subdivision,
+    },
 };
 
 //
 #[derive(Debug, Clone)]
 pub struct Json {
     pub query: Box<str>,
-    pub key: Option<Box<str>>,
+    pub key: Option<ApiKey>,
     pub fields: Option<Box<str>>,
     pub lang: Option<Lang>,
 }
 
 impl Json {
-    pub fn new(query: impl AsRef<str>, key: Option<Box<str>>) -> Self {
+    pub fn new(query: impl AsRef<str>, key: Option<ApiKey>) -> Self {
         Self {
             query: query.as_ref().into(),
             key,
@@ -70,7 +82,8 @@ impl Endpoint for Json {
         let mut url = Url::parse(url.as_str()).map_err(EndpointError::MakeRequestUrlFailed)?;
 
         if let Some(key) = &self.key {
-            url.query_pairs_mut().append_pair("key", key);
+            url.query_pairs_mut()
+                .append_pair(URL_QUERY_KEY, key.expose());
         }
         if let Some(fields) = &self.fields {
             url.query_pairs_mut().append_pair("fields", fields);
@@ -94,21 +107,20 @@ impl Endpoint for Json {
         &self,
         response: Response<Body>,
     ) -> Result<Self::ParseResponseOutput, Self::ParseResponseError> {
-        let json = serde_json::from_slice(response.body())
+        let json: JsonResponseBodyJson = serde_json::from_slice(response.body())
             .map_err(EndpointError::DeResponseBodyJsonFailed)?;
 
+        if self.key.is_some()
+            && let Some(fail_json) = json.as_fail()
+            && fail_json.reason() == FailReason::KeyInvalid
+        {
+            return Err(EndpointError::KeyInvalid(fail_json.message.clone()));
+        }
+
         let rate_limit = if self.key.is_some() {
             None
         } else {
-            Some(RateLimit {
-                remaining: get_n_from_headers_by_key(response.headers(), RESPONSE_HEADER_KEY_X_RL)
-                    .ok(),
-                seconds_until_reset: get_n_from_headers_by_key(
-                    response.headers(),
-                    RESPONSE_HEADER_KEY_X_TTL,
-                )
-                .ok(),
-            })
+            Some(get_rate_limit_from_headers(response.headers()))
         };
 
         Ok((json, rate_limit))
@@ -118,7 +130,8 @@ impl Endpoint for Json {
 //
 //
 //
-#[derive(Debug, Clone)]
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "status", rename_all = "lowercase")]
 pub enum JsonResponseBodyJson {
     Success(Box<JsonResponseBodySuccessJson>),
     Fail(JsonResponseBodyFailJson),
@@ -129,32 +142,32 @@ impl<'de> Deserialize<'de> for JsonResponseBodyJson {
     where
         D: Deserializer<'de>,
     {
-        let map = Map::deserialize(deserializer)?;
-
-        if let Some(status) = map.get("status") {
-            let status: Box<str> = Deserialize::deserialize(status).map_err(de::Error::custom)?;
-            match status.as_ref() {
-                "success" => JsonResponseBodySuccessJson::deserialize(Value::Object(map))
-                    .map(|x| JsonResponseBodyJson::Success(x.into()))
-                    .map_err(de::Error::custom),
-                "fail" => JsonResponseBodyFailJson::deserialize(Value::Object(map))
-                    .map(JsonResponseBodyJson::Fail)
-                    .map_err(de::Error::custom),
-                s => Err(de::Error::custom(format!("status [{}] mismatch", s))),
-            }
-        } else if map.get("message").is_some() {
-            JsonResponseBodyFailJson::deserialize(Value::Object(map))
-                .map(JsonResponseBodyJson::Fail)
-                .map_err(de::Error::custom)
-        } else {
-            JsonResponseBodySuccessJson::deserialize(Value::Object(map))
-                .map(|x| JsonResponseBodyJson::Success(x.into()))
-                .map_err(de::Error::custom)
-        }
+        JsonResponseBodyJsonRef::deserialize(deserializer).map(Into::into)
     }
 }
 
 impl JsonResponseBodyJson {
+    /// Deserialize, failing on any field not known to this crate, e.g. for contract tests.
+    pub fn from_slice_strict(slice: &[u8]) -> Result<Self, SerdeJsonError> {
+        let json: Self = serde_json::from_slice(slice)?;
+        json.deny_extra()?;
+        Ok(json)
+    }
+
+    pub fn extra(&self) -> &Map<String, Value> {
+        match self {
+            Self::Success(x) => &x.extra,
+            Self::Fail(x) => &x.extra,
+        }
+    }
+
+    pub(crate) fn deny_extra(&self) -> Result<(), SerdeJsonError> {
+        match self.extra().keys().next() {
+            Some(k) => Err(de::Error::custom(format!("unknown field `{}`", k))),
+            None => Ok(()),
+        }
+    }
+
     pub fn is_success(&self) -> bool {
         matches!(self, Self::Success(_))
     }
@@ -174,85 +187,318 @@ impl JsonResponseBodyJson {
     }
 }
 
-#[derive(Deserialize, Debug, Clone)]
+/// Wire names of the `JsonResponseBodySuccessJson` fields.
+pub const SUCCESS_FIELDS: &[&str] = &[
+    "query",
+    "continent",
+    "continentCode",
+    "country",
+    "countryCode",
+    "countryCode3",
+    "region",
+    "regionName",
+    "city",
+    "district",
+    "zip",
+    "lat",
+    "lon",
+    "timezone",
+    "offset",
+    "currentTime",
+    "currency",
+    "callingCode",
+    "isp",
+    "org",
+    "as",
+    "asname",
+    "reverse",
+    "mobile",
+    "proxy",
+    "hosting",
+];
+
+/// Which of `SUCCESS_FIELDS` a response had, the others were not in `fields` and hold defaults.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PresentFields(u32);
+
+impl PresentFields {
+    pub const NONE: Self = Self(0);
+    pub const ALL: Self = Self((1 << SUCCESS_FIELDS.len()) - 1);
+
+    fn bit(name: &str) -> u32 {
+        SUCCESS_FIELDS
+            .iter()
+            .position(|x| *x == name)
+            .map_or(0, |i| 1 << i)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.0 & Self::bit(name) != 0
+    }
+
+    /// Names not in `SUCCESS_FIELDS` are ignored.
+    pub fn insert(&mut self, name: &str) {
+        self.0 |= Self::bit(name);
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.0 &= !Self::bit(name);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &'static str> + '_ {
+        SUCCESS_FIELDS.iter().copied().filter(|x| self.contains(x))
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct JsonResponseBodySuccessJson {
-    #[serde(default = "serde_field_default::default_ip_addr")]
     pub query: IpAddr,
     //
-    #[serde(default)]
     pub continent: Box<str>,
-    #[serde(default, rename = "continentCode")]
     pub continent_code: ContinentCode,
     //
-    #[serde(default)]
     pub country: Box<str>,
-    #[serde(default, rename = "countryCode")]
     pub country_code: CountryCode,
-    #[serde(default, rename = "countryCode3")]
     pub country_code3: Box<str>,
     //
-    #[serde(default)]
     pub region: Box<str>,
-    #[serde(default, rename = "regionName")]
     pub region_name: Box<str>,
     //
-    #[serde(default)]
     pub city: Box<str>,
-    #[serde(default)]
     pub district: Box<str>,
     //
-    #[serde(default)]
     pub zip: Box<str>,
     //
-    #[serde(default)]
     pub lat: f64,
-    #[serde(default)]
     pub lon: f64,
     //
-    #[serde(
-        default = "serde_field_default::chrono_tz::default_tz",
-        deserialize_with = "serde_field_with::from_str"
-    )]
     pub timezone: Tz,
-    #[serde(default)]
     pub offset: isize,
-    #[serde(
-        default = "serde_field_default::chrono::default_date_time_utc",
-        rename = "currentTime"
-    )]
-    pub current_time: DateTime<Utc>,
+    pub current_time: DateTime<FixedOffset>,
     //
-    #[serde(default)]
-    pub currency: Box<str>,
+    pub currency: Currency,
     //
-    #[serde(default, rename = "callingCode")]
-    pub calling_code: Box<str>,
+    pub calling_code: CallingCode,
     //
-    #[serde(default)]
     pub isp: Box<str>,
-    #[serde(default)]
     pub org: Box<str>,
-    #[serde(default)]
     pub r#as: Box<str>,
-    #[serde(default)]
     pub asname: Box<str>,
-    #[serde(default)]
     pub reverse: Box<str>,
     //
-    #[serde(default)]
     pub mobile: bool,
-    #[serde(default)]
     pub proxy: bool,
-    #[serde(default)]
     pub hosting: bool,
+    //
+    /// Only these are serialized, set it when filling in a field that was absent.
+    pub present: PresentFields,
+    /// Fields not known to this crate yet.
+    pub extra: Map<String, Value>,
+}
+
+impl Serialize for JsonResponseBodySuccessJson {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        macro_rules! entry {
+            ($name:literal, $value:expr) => {
+                if self.present.contains($name) {
+                    map.serialize_entry($name, $value)?;
+                }
+            };
+        }
+        entry!("query", &self.query);
+        entry!("continent", &self.continent);
+        entry!("continentCode", &self.continent_code);
+        entry!("country", &self.country);
+        entry!("countryCode", &self.country_code);
+        entry!("countryCode3", &self.country_code3);
+        entry!("region", &self.region);
+        entry!("regionName", &self.region_name);
+        entry!("city", &self.city);
+        entry!("district", &self.district);
+        entry!("zip", &self.zip);
+        entry!("lat", &self.lat);
+        entry!("lon", &self.lon);
+        entry!("timezone", self.timezone.name());
+        entry!("offset", &self.offset);
+        entry!("currentTime", &self.current_time);
+        entry!("currency", &self.currency);
+        entry!("callingCode", &self.calling_code);
+        entry!("isp", &self.isp);
+        entry!("org", &self.org);
+        entry!("as", &self.r#as);
+        entry!("asname", &self.asname);
+        entry!("reverse", &self.reverse);
+        entry!("mobile", &self.mobile);
+        entry!("proxy", &self.proxy);
+        entry!("hosting", &self.hosting);
+        for (k, v) in &self.extra {
+            map.serialize_entry(k, v)?;
+        }
+        map.end()
+    }
 }
 
-#[derive(Deserialize, Debug, Clone)]
+impl<'de> Deserialize<'de> for JsonResponseBodySuccessJson {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match JsonResponseBodyJsonRef::deserialize(deserializer)? {
+            JsonResponseBodyJsonRef::Success(x) => Ok((*x).into()),
+            JsonResponseBodyJsonRef::Fail(x) => Err(de::Error::custom(format!(
+                "status [fail] mismatch, message [{}]",
+                x.message
+            ))),
+        }
+    }
+}
+
+impl JsonResponseBodySuccessJson {
+    /// Parsed `as`, `None` when it was not in `fields` or is empty.
+    pub fn autonomous_system(&self) -> Option<AutonomousSystem> {
+        self.r#as.parse().ok()
+    }
+
+    pub fn asn(&self) -> Option<Asn> {
+        self.r#as.split(' ').next()?.parse().ok()
+    }
+
+    /// `None` when `lat` or `lon` was not in `fields`.
+    pub fn geo_point(&self) -> Option<GeoPoint> {
+        if !(self.present.contains("lat") && self.present.contains("lon")) {
+            return None;
+        }
+        let point = GeoPoint::new(self.lat, self.lon);
+        point.is_valid().then_some(point)
+    }
+
+    /// ISO 3166-2 code from `countryCode` and `region`, e.g. "CA-QC",
+    /// `None` when either was not in `fields` or the pair is not in the bundled table.
+    pub fn subdivision_code(&self) -> Option<SubdivisionCode> {
+        subdivision::subdivision_code(&self.country_code, &self.region)
+    }
+
+    pub fn current_time_utc(&self) -> DateTime<Utc> {
+        self.current_time.to_utc()
+    }
+
+    pub fn current_time_tz(&self) -> DateTime<Tz> {
+        self.current_time.with_timezone(&self.timezone)
+    }
+
+    /// Check `offset`, `timezone` and `currentTime` agree with each other,
+    /// only meaningful when all three are in `fields`.
+    pub fn check_time_consistency(&self) -> Result<(), TimeInconsistency> {
+        let current_time_offset = self.current_time.offset().local_minus_utc();
+        if self.offset != current_time_offset as isize {
+            return Err(TimeInconsistency::OffsetMismatch {
+                offset: self.offset,
+                current_time_offset,
+            });
+        }
+
+        let timezone_offset = self
+            .timezone
+            .offset_from_utc_datetime(&self.current_time.naive_utc())
+            .fix()
+            .local_minus_utc();
+        if self.offset != timezone_offset as isize {
+            return Err(TimeInconsistency::TimezoneMismatch {
+                offset: self.offset,
+                timezone: self.timezone,
+                timezone_offset,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimeInconsistency {
+    /// `offset` differs from the offset of `currentTime`.
+    OffsetMismatch {
+        offset: isize,
+        current_time_offset: i32,
+    },
+    /// `offset` differs from the offset of `timezone` at `currentTime`.
+    TimezoneMismatch {
+        offset: isize,
+        timezone: Tz,
+        timezone_offset: i32,
+    },
+}
+
+impl fmt::Display for TimeInconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for TimeInconsistency {}
+
+#[derive(Serialize, Debug, Clone)]
 pub struct JsonResponseBodyFailJson {
-    #[serde(default)]
+    #[serde(skip_serializing_if = "str::is_empty")]
     pub query: Box<str>,
     //
     pub message: Box<str>,
+    //
+    /// Fields not known to this crate yet.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl<'de> Deserialize<'de> for JsonResponseBodyFailJson {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match JsonResponseBodyJsonRef::deserialize(deserializer)? {
+            JsonResponseBodyJsonRef::Success(_) => {
+                Err(de::Error::custom("missing field `message`"))
+            }
+            JsonResponseBodyJsonRef::Fail(x) => Ok(x.into()),
+        }
+    }
+}
+
+impl JsonResponseBodyFailJson {
+    pub fn reason(&self) -> FailReason {
+        FailReason::from_message(&self.message)
+    }
+}
+
+/// Classification of the fail `message`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FailReason {
+    PrivateRange,
+    ReservedRange,
+    InvalidQuery,
+    /// "SSL unavailable for this endpoint, order a key at https://members.ip-api.com/"
+    SslUnavailable,
+    /// "invalid/expired key, renew at https://members.ip-api.com/order"
+    KeyInvalid,
+    Other,
+}
+
+impl FailReason {
+    pub fn from_message(message: &str) -> Self {
+        match message {
+            "private range" => Self::PrivateRange,
+            "reserved range" => Self::ReservedRange,
+            "invalid query" => Self::InvalidQuery,
+            s if s.starts_with("SSL unavailable") => Self::SslUnavailable,
+            s if s.starts_with("invalid/expired key") || s.starts_with("invalid key") => {
+                Self::KeyInvalid
+            }
+            _ => Self::Other,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -269,6 +515,7 @@ mod tests {
         let json = Json::new("24.48.0.1", Some("foo".into()));
         let req = json.render_request().unwrap();
         assert_eq!(req.uri(), "https://pro.ip-api.com/json/24.48.0.1?key=foo");
+        assert!(!format!("{:?}", json).contains("foo"));
 
         let json = json.fields("status,message,country,query");
         let req = json.render_request().unwrap();
@@ -293,7 +540,11 @@ mod tests {
             Ok(JsonResponseBodyJson::Success(ok_json)) => {
                 assert_eq!(ok_json.query.to_string(), "24.48.0.1");
                 assert_eq!(ok_json.continent_code, ContinentCode::AS);
+                assert!(!ok_json.present.contains("continentCode"));
+                assert!(ok_json.present.contains("query"));
+                assert_eq!(ok_json.present.iter().count(), 13);
                 assert_eq!(ok_json.country_code, CountryCode::CA);
+                assert_eq!(ok_json.currency, Currency::default());
             }
             ret => panic!("{:?}", ret),
         }
@@ -305,6 +556,15 @@ mod tests {
                 assert_eq!(ok_json.query.to_string(), "24.48.0.1");
                 assert_eq!(ok_json.continent_code, ContinentCode::NA);
                 assert_eq!(ok_json.country_code, CountryCode::CA);
+                assert_eq!(ok_json.asn(), Some(Asn(5769)));
+                assert_eq!(ok_json.currency, Currency::CAD);
+                assert_eq!(ok_json.calling_code, CallingCode::Code(1));
+                assert_eq!(ok_json.subdivision_code().unwrap().to_string(), "CA-QC");
+                assert_eq!(ok_json.geo_point(), Some(GeoPoint::new(45.4995, -73.5848)));
+                assert_eq!(
+                    ok_json.autonomous_system().unwrap().name,
+                    "Videotron Telecom Ltee".into()
+                );
             }
             ret => panic!("{:?}", ret),
         }
@@ -316,6 +576,8 @@ mod tests {
                 assert_eq!(ok_json.query.to_string(), "24.48.0.1");
                 assert_eq!(ok_json.continent_code, ContinentCode::NA);
                 assert_eq!(ok_json.country_code, CountryCode::CA);
+                assert_eq!(ok_json.currency, Currency::CAD);
+                assert_eq!(ok_json.calling_code, CallingCode::Code(1));
             }
             ret => panic!("{:?}", ret),
         }
@@ -326,6 +588,7 @@ mod tests {
         )) {
             Ok(JsonResponseBodyJson::Fail(err_json)) => {
                 assert_eq!(err_json.query, "".into());
+                assert_eq!(err_json.reason(), FailReason::SslUnavailable);
             }
             ret => panic!("{:?}", ret),
         }
@@ -335,6 +598,7 @@ mod tests {
         )) {
             Ok(JsonResponseBodyJson::Fail(err_json)) => {
                 assert_eq!(err_json.query, "24".into());
+                assert_eq!(err_json.reason(), FailReason::InvalidQuery);
             }
             ret => panic!("{:?}", ret),
         }
@@ -344,8 +608,173 @@ mod tests {
         )) {
             Ok(JsonResponseBodyJson::Fail(err_json)) => {
                 assert_eq!(err_json.query, "".into());
+                assert_eq!(err_json.reason(), FailReason::KeyInvalid);
             }
             ret => panic!("{:?}", ret),
         }
     }
+
+    #[test]
+    fn test_parse_response_with_key_invalid() {
+        let body = include_bytes!("../../tests/response_body_json_files/json_err_3.json");
+
+        let json = Json::new("24.48.0.1", Some("foo".into()));
+        let res = Response::builder().status(403).body(body.to_vec()).unwrap();
+        match json.parse_response(res) {
+            Err(EndpointError::KeyInvalid(message)) => {
+                assert!(message.starts_with("invalid/expired key"))
+            }
+            ret => panic!("{:?}", ret),
+        }
+
+        let json = Json::new("24.48.0.1", None);
+        let res = Response::builder().status(403).body(body.to_vec()).unwrap();
+        match json.parse_response(res) {
+            Ok((JsonResponseBodyJson::Fail(_), Some(_))) => {}
+            ret => panic!("{:?}", ret),
+        }
+    }
+
+    #[test]
+    fn test_de_response_body_json_with_status_mismatch() {
+        let err = serde_json::from_str::<JsonResponseBodyJson>(r#"{"status":"foo"}"#).unwrap_err();
+        assert!(err.to_string().contains("status [foo] mismatch"));
+
+        let err = serde_json::from_str::<JsonResponseBodyJson>(r#"{"status":"fail"}"#).unwrap_err();
+        assert!(err.to_string().contains("missing field `message`"));
+
+        let err =
+            serde_json::from_str::<JsonResponseBodyJson>(r#"{"status":"success","query":"24"}"#)
+                .unwrap_err();
+        assert!(err.to_string().contains("query [24] invalid"));
+    }
+
+    #[test]
+    fn test_ser_response_body_json() {
+        for s in [
+            include_str!("../../tests/response_body_json_files/json_default.json"),
+            include_str!("../../tests/response_body_json_files/json_full_fields.json"),
+            include_str!(
+                "../../tests/response_body_json_files/json_full_fields_and_zh-CN_lang.json"
+            ),
+            include_str!("../../tests/response_body_json_files/json_err_1.json"),
+            include_str!("../../tests/response_body_json_files/json_err_2.json"),
+            include_str!("../../tests/response_body_json_files/json_err_3.json"),
+        ] {
+            let expected = serde_json::from_str::<serde_json::Value>(s).unwrap();
+            let json = serde_json::from_str::<JsonResponseBodyJson>(s).unwrap();
+            let value = serde_json::to_value(&json).unwrap();
+            assert_eq!(value, expected);
+
+            let json = serde_json::from_value::<JsonResponseBodyJson>(value.clone()).unwrap();
+            assert_eq!(serde_json::to_value(&json).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_de_response_body_json_with_extra() {
+        let s = br#"{"status":"success","query":"24.48.0.1","countryCode":"CA","foo":{"bar":1}}"#;
+        match serde_json::from_slice::<JsonResponseBodyJson>(s) {
+            Ok(JsonResponseBodyJson::Success(ok_json)) => {
+                assert_eq!(ok_json.extra.len(), 1);
+                assert_eq!(ok_json.extra["foo"], serde_json::json!({"bar": 1}));
+
+                let value = serde_json::to_value(JsonResponseBodyJson::Success(ok_json)).unwrap();
+                assert_eq!(value["foo"]["bar"], 1);
+            }
+            ret => panic!("{:?}", ret),
+        }
+        let err = JsonResponseBodyJson::from_slice_strict(s).unwrap_err();
+        assert!(err.to_string().contains("unknown field `foo`"));
+
+        let s = br#"{"status":"fail","message":"invalid query","foo":1}"#;
+        let json = serde_json::from_slice::<JsonResponseBodyFailJson>(s).unwrap();
+        assert_eq!(json.extra["foo"], 1);
+        assert!(JsonResponseBodyJson::from_slice_strict(s).is_err());
+
+        //
+        let json = serde_json::from_slice::<JsonResponseBodySuccessJson>(
+            br#"{"status":"success","query":"24.48.0.1"}"#,
+        )
+        .unwrap();
+        assert!(json.extra.is_empty());
+        assert!(serde_json::from_slice::<JsonResponseBodySuccessJson>(s).is_err());
+
+        //
+        for s in [
+            &include_bytes!("../../tests/response_body_json_files/json_default.json")[..],
+            include_bytes!("../../tests/response_body_json_files/json_full_fields.json"),
+            include_bytes!(
+                "../../tests/response_body_json_files/json_full_fields_and_zh-CN_lang.json"
+            ),
+            include_bytes!("../../tests/response_body_json_files/json_err_1.json"),
+            include_bytes!("../../tests/response_body_json_files/json_err_2.json"),
+            include_bytes!("../../tests/response_body_json_files/json_err_3.json"),
+        ] {
+            JsonResponseBodyJson::from_slice_strict(s).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_current_time() {
+        let mut ok_json = serde_json::from_str::<JsonResponseBodySuccessJson>(include_str!(
+            "../../tests/response_body_json_files/json_full_fields.json"
+        ))
+        .unwrap();
+        assert_eq!(
+            ok_json.current_time.to_string(),
+            "2022-04-02 02:06:22 -04:00"
+        );
+        assert_eq!(
+            ok_json.current_time_utc().to_string(),
+            "2022-04-02 06:06:22 UTC"
+        );
+        assert_eq!(
+            ok_json.current_time_tz().to_string(),
+            "2022-04-02 02:06:22 EDT"
+        );
+        assert_eq!(ok_json.check_time_consistency(), Ok(()));
+
+        ok_json.offset = -18000;
+        assert_eq!(
+            ok_json.check_time_consistency(),
+            Err(TimeInconsistency::OffsetMismatch {
+                offset: -18000,
+                current_time_offset: -14400
+            })
+        );
+
+        ok_json.offset = -14400;
+        ok_json.timezone = Tz::America__Chicago;
+        assert_eq!(
+            ok_json.check_time_consistency(),
+            Err(TimeInconsistency::TimezoneMismatch {
+                offset: -14400,
+                timezone: Tz::America__Chicago,
+                timezone_offset: -18000
+            })
+        );
+    }
+
+    #[test]
+    fn test_geo_point_not_in_fields() {
+        let ok_json = serde_json::from_str::<JsonResponseBodySuccessJson>(
+            r#"{"status":"success","query":"24.48.0.1"}"#,
+        )
+        .unwrap();
+        assert_eq!(ok_json.geo_point(), None);
+
+        let ok_json = serde_json::from_str::<JsonResponseBodySuccessJson>(
+            r#"{"status":"success","lat":45.4995,"query":"24.48.0.1"}"#,
+        )
+        .unwrap();
+        assert_eq!(ok_json.geo_point(), None);
+
+        // Null Island is a location.
+        let ok_json = serde_json::from_str::<JsonResponseBodySuccessJson>(
+            r#"{"status":"success","lat":0,"lon":0,"query":"24.48.0.1"}"#,
+        )
+        .unwrap();
+        assert_eq!(ok_json.geo_point(), Some(GeoPoint::new(0.0, 0.0)));
+    }
 }
@@ -0,0 +1,87 @@
+//! Check a pro key up front, with the cheapest possible json request.
+
+use http_api_client_endpoint::{Body, Endpoint, Request, Response};
+
+use crate::{
+    endpoints::{common::EndpointError, json::Json},
+    types::api_key::ApiKey,
+};
+
+pub const FIELDS: &str = "status,message";
+
+//
+#[derive(Debug, Clone)]
+pub struct KeyProbe {
+    inner: Json,
+}
+
+impl KeyProbe {
+    pub fn new(key: ApiKey) -> Self {
+        Self {
+            inner: Json::new("", Some(key)).fields(FIELDS),
+        }
+    }
+
+    pub fn key(&self) -> &ApiKey {
+        self.inner.key.as_ref().expect("Never")
+    }
+}
+
+impl Endpoint for KeyProbe {
+    type RenderRequestError = EndpointError;
+
+    type ParseResponseOutput = ();
+    type ParseResponseError = EndpointError;
+
+    fn render_request(&self) -> Result<Request<Body>, Self::RenderRequestError> {
+        self.inner.render_request()
+    }
+
+    /// Any response other than `invalid/expired key` means the key was accepted,
+    /// e.g. `private range` when the probing host has no public address.
+    fn parse_response(
+        &self,
+        response: Response<Body>,
+    ) -> Result<Self::ParseResponseOutput, Self::ParseResponseError> {
+        self.inner.parse_response(response).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_request() {
+        let probe = KeyProbe::new("foo".into());
+        let req = probe.render_request().unwrap();
+        assert_eq!(
+            req.uri(),
+            "https://pro.ip-api.com/json/?key=foo&fields=status%2Cmessage"
+        );
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let probe = KeyProbe::new("foo".into());
+
+        let res = Response::builder()
+            .body(br#"{"status":"success"}"#.to_vec())
+            .unwrap();
+        probe.parse_response(res).unwrap();
+
+        let res = Response::builder()
+            .body(br#"{"status":"fail","message":"private range"}"#.to_vec())
+            .unwrap();
+        probe.parse_response(res).unwrap();
+
+        let res = Response::builder()
+            .status(403)
+            .body(include_bytes!("../../tests/response_body_json_files/json_err_3.json").to_vec())
+            .unwrap();
+        match probe.parse_response(res) {
+            Err(EndpointError::KeyInvalid(_)) => {}
+            ret => panic!("{:?}", ret),
+        }
+    }
+}
@@ -4,7 +4,10 @@ mod helper;
 
 //
 pub mod batch;
+pub mod borrowed;
+pub mod fields;
 pub mod json;
+pub mod key_probe;
 
 //
 pub const URL_BASE: &str = "http://ip-api.com";
@@ -1,3 +1,16 @@
+pub mod access_log;
+#[cfg(feature = "client")]
+pub mod client;
+#[cfg(feature = "client_ip")]
+pub mod client_ip;
 pub mod endpoints;
+pub mod lookup_cache;
+#[cfg(feature = "mmdb")]
+pub mod mmdb;
 pub mod objects;
+pub mod policy;
+#[cfg(feature = "prefix")]
+pub mod prefix;
+pub mod risk;
+pub mod routing;
 pub mod types;
@@ -0,0 +1,164 @@
+//! Lookup results keyed by IP address, optionally persisted as JSON lines.
+//!
+//! Every insert appends one `{"ip":..,"result":..}` line, so an interrupted bulk lookup resumes
+//! from the file. Lines that do not parse, e.g. a torn last line, are skipped on open.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{File, OpenOptions},
+    io::{self, BufRead as _, BufReader, Write as _},
+    net::IpAddr,
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::endpoints::json::JsonResponseBodyJson;
+
+//
+#[derive(Debug, Default)]
+pub struct LookupCache {
+    entries: HashMap<IpAddr, JsonResponseBodyJson>,
+    file: Option<File>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Line<T> {
+    ip: IpAddr,
+    result: T,
+}
+
+impl LookupCache {
+    /// In memory only.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads `path` if it exists, later inserts are appended to it.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut entries = HashMap::new();
+        let mut torn = false;
+        match File::open(path) {
+            Ok(file) => {
+                let mut reader = BufReader::new(file);
+                let mut line = String::new();
+                while reader.read_line(&mut line)? > 0 {
+                    torn = !line.ends_with('\n');
+                    if let Ok(Line { ip, result }) = serde_json::from_str(&line) {
+                        entries.insert(ip, result);
+                    }
+                    line.clear();
+                }
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        if torn {
+            file.write_all(b"\n")?;
+        }
+        Ok(Self {
+            entries,
+            file: Some(file),
+        })
+    }
+
+    pub fn get(&self, ip: &IpAddr) -> Option<&JsonResponseBodyJson> {
+        self.entries.get(ip)
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        self.entries.contains_key(ip)
+    }
+
+    pub fn insert(&mut self, ip: IpAddr, result: JsonResponseBodyJson) -> io::Result<()> {
+        if let Some(file) = &mut self.file {
+            let mut buf = serde_json::to_vec(&Line {
+                ip,
+                result: &result,
+            })?;
+            buf.push(b'\n');
+            // One write per line, a crash leaves at most one torn line behind.
+            file.write_all(&buf)?;
+        }
+        self.entries.insert(ip, result);
+        Ok(())
+    }
+
+    /// `ips` not in the cache yet, deduplicated, in order of first appearance.
+    pub fn missing<'a>(&self, ips: impl IntoIterator<Item = &'a IpAddr>) -> Vec<IpAddr> {
+        let mut missing: Vec<IpAddr> = vec![];
+        let mut seen = HashSet::new();
+        for ip in ips {
+            if !self.contains(ip) && seen.insert(*ip) {
+                missing.push(*ip);
+            }
+        }
+        missing
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(ip: &str) -> JsonResponseBodyJson {
+        serde_json::from_str(&format!(
+            r#"{{"status":"success","countryCode":"CA","as":"AS5769 Videotron Telecom Ltee","query":"{}"}}"#,
+            ip
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.jsonl");
+        let a: IpAddr = "24.48.0.1".parse().unwrap();
+        let b: IpAddr = "10.0.0.1".parse().unwrap();
+
+        let mut cache = LookupCache::open(&path).unwrap();
+        assert!(cache.is_empty());
+        cache.insert(a, result("24.48.0.1")).unwrap();
+        assert_eq!(cache.missing(&[a, b, b]), [b]);
+        drop(cache);
+
+        // A torn last line is skipped.
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        write!(file, r#"{{"ip":"10.0.0.1","result":{{"sta"#).unwrap();
+        drop(file);
+
+        let mut cache = LookupCache::open(&path).unwrap();
+        assert_eq!(cache.len(), 1);
+        assert!(
+            cache
+                .get(&a)
+                .and_then(|x| x.as_success())
+                .is_some_and(|x| x.asn() == Some(5769.into()))
+        );
+        assert!(!cache.contains(&b));
+        cache.insert(b, result("10.0.0.1")).unwrap();
+        drop(cache);
+
+        assert_eq!(LookupCache::open(&path).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_in_memory() {
+        let mut cache = LookupCache::new();
+        let ip: IpAddr = "24.48.0.1".parse().unwrap();
+        cache.insert(ip, result("24.48.0.1")).unwrap();
+        assert!(cache.contains(&ip));
+        assert!(cache.missing(&[ip]).is_empty());
+    }
+}
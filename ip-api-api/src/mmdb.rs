@@ -0,0 +1,576 @@
+//! Export lookup results as a MaxMind DB, e.g. for air-gapped hosts.
+//!
+//! https://maxmind.github.io/MaxMind-DB/
+//!
+//! Records follow the GeoLite2-City schema, plus the `autonomous_system_number` and
+//! `autonomous_system_organization` of GeoLite2-ASN. Empty fields, i.e. not in `fields`, are left
+//! out. The tree is IPv6, with IPv4 in `::/96` like GeoLite2.
+
+use core::fmt;
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use ipnet::IpNet;
+
+use crate::{endpoints::json::JsonResponseBodySuccessJson, prefix::Prefix, types::lang::Lang};
+
+pub const DATABASE_TYPE_DEFAULT: &str = "GeoLite2-City";
+pub const DESCRIPTION_DEFAULT: &str = "ip-api.com lookup results";
+pub const METADATA_START_MARKER: &[u8] = b"\xAB\xCD\xEFMaxMind.com";
+
+const DATA_SECTION_SEPARATOR_SIZE: usize = 16;
+
+//
+pub struct MmdbWriter {
+    database_type: Box<str>,
+    description: Box<str>,
+    lang: Lang,
+    build_epoch: Option<u64>,
+    entries: Vec<(IpNet, Value)>,
+}
+
+impl fmt::Debug for MmdbWriter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MmdbWriter")
+            .field("database_type", &self.database_type)
+            .field("description", &self.description)
+            .field("lang", &self.lang)
+            .field("build_epoch", &self.build_epoch)
+            .field("entries", &self.entries.len())
+            .finish()
+    }
+}
+
+impl Default for MmdbWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MmdbWriter {
+    pub fn new() -> Self {
+        Self {
+            database_type: DATABASE_TYPE_DEFAULT.into(),
+            description: DESCRIPTION_DEFAULT.into(),
+            lang: Lang::EN,
+            build_epoch: None,
+            entries: vec![],
+        }
+    }
+
+    pub fn database_type(mut self, database_type: impl AsRef<str>) -> Self {
+        self.database_type = database_type.as_ref().into();
+        self
+    }
+
+    pub fn description(mut self, description: impl AsRef<str>) -> Self {
+        self.description = description.as_ref().into();
+        self
+    }
+
+    /// The language of the names, i.e. the `lang` they were looked up with.
+    pub fn lang(mut self, lang: Lang) -> Self {
+        self.lang = lang;
+        self
+    }
+
+    /// Seconds since the epoch, now by default.
+    pub fn build_epoch(mut self, build_epoch: u64) -> Self {
+        self.build_epoch = Some(build_epoch);
+        self
+    }
+
+    /// An IP address or prefix, more specific prefixes win, otherwise the last insert.
+    pub fn insert(&mut self, prefix: impl Into<Prefix>, record: &JsonResponseBodySuccessJson) {
+        let value = city(record, &self.lang.to_string());
+        for net in prefix.into().nets() {
+            self.entries.push((net, value.clone()));
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn write(&self, mut w: impl Write) -> io::Result<()> {
+        w.write_all(&self.to_vec())
+    }
+
+    pub fn to_vec(&self) -> Vec<u8> {
+        // Identical records are stored once.
+        let mut data = vec![];
+        let mut offsets = HashMap::new();
+        let mut entries = self
+            .entries
+            .iter()
+            .map(|(net, value)| {
+                let mut buf = vec![];
+                value.encode(&mut buf);
+                let offset = *offsets.entry(buf).or_insert_with_key(|buf| {
+                    data.extend_from_slice(buf);
+                    data.len() - buf.len()
+                });
+                let (bits, len) = tree_key(net);
+                (bits, len, offset)
+            })
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|(_, len, _)| *len);
+
+        let nodes = tree(&entries);
+        let node_count = nodes.len();
+        let max = node_count + DATA_SECTION_SEPARATOR_SIZE + data.len();
+        let record_size = if max < 1 << 24 {
+            24
+        } else if max < 1 << 28 {
+            28
+        } else {
+            32
+        };
+
+        let mut buf = Vec::with_capacity(node_count * record_size / 4 + max);
+        for [left, right] in &nodes {
+            let value = |record: &Record| match *record {
+                Record::Empty => node_count as u32,
+                Record::Node(n) => n as u32,
+                Record::Data(offset) => (node_count + DATA_SECTION_SEPARATOR_SIZE + offset) as u32,
+            };
+            let (left, right) = (value(left), value(right));
+            match record_size {
+                24 => {
+                    buf.extend_from_slice(&left.to_be_bytes()[1..]);
+                    buf.extend_from_slice(&right.to_be_bytes()[1..]);
+                }
+                28 => {
+                    buf.extend_from_slice(&left.to_be_bytes()[1..]);
+                    buf.push((((left >> 24) & 0x0F) << 4) as u8 | ((right >> 24) & 0x0F) as u8);
+                    buf.extend_from_slice(&right.to_be_bytes()[1..]);
+                }
+                _ => {
+                    buf.extend_from_slice(&left.to_be_bytes());
+                    buf.extend_from_slice(&right.to_be_bytes());
+                }
+            }
+        }
+        buf.extend_from_slice(&[0; DATA_SECTION_SEPARATOR_SIZE]);
+        buf.extend_from_slice(&data);
+
+        buf.extend_from_slice(METADATA_START_MARKER);
+        let build_epoch = self.build_epoch.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|x| x.as_secs())
+                .unwrap_or_default()
+        });
+        let lang = self.lang.to_string();
+        Value::Map(vec![
+            ("binary_format_major_version".into(), Value::U16(2)),
+            ("binary_format_minor_version".into(), Value::U16(0)),
+            ("build_epoch".into(), Value::U64(build_epoch)),
+            (
+                "database_type".into(),
+                Value::String(self.database_type.clone()),
+            ),
+            (
+                "description".into(),
+                Value::Map(vec![(
+                    lang.as_str().into(),
+                    Value::String(self.description.clone()),
+                )]),
+            ),
+            ("ip_version".into(), Value::U16(6)),
+            (
+                "languages".into(),
+                Value::Array(vec![Value::String(lang.into())]),
+            ),
+            ("node_count".into(), Value::U32(node_count as u32)),
+            ("record_size".into(), Value::U16(record_size as u16)),
+        ])
+        .encode(&mut buf);
+
+        buf
+    }
+}
+
+//
+//
+//
+#[derive(Debug, Copy, Clone)]
+enum Record {
+    Empty,
+    Node(usize),
+    /// Offset in the data section.
+    Data(usize),
+}
+
+/// IPv4 in `::/96`.
+fn tree_key(net: &IpNet) -> (u128, u8) {
+    match net {
+        IpNet::V4(net) => (u32::from(net.network()).into(), net.prefix_len() + 96),
+        IpNet::V6(net) => (net.network().into(), net.prefix_len()),
+    }
+}
+
+/// `entries` are `(bits, prefix_len, offset)`, least specific first.
+fn tree(entries: &[(u128, u8, usize)]) -> Vec<[Record; 2]> {
+    let mut nodes = vec![[Record::Empty; 2]];
+    for &(bits, len, offset) in entries {
+        if len == 0 {
+            nodes[0] = [Record::Data(offset); 2];
+            continue;
+        }
+
+        let mut node = 0;
+        for i in 0..len {
+            let bit = ((bits >> (127 - i)) & 1) as usize;
+            if i == len - 1 {
+                nodes[node][bit] = Record::Data(offset);
+                break;
+            }
+            node = match nodes[node][bit] {
+                Record::Node(n) => n,
+                // Split, the less specific record keeps covering the rest.
+                record => {
+                    nodes.push([record; 2]);
+                    nodes[node][bit] = Record::Node(nodes.len() - 1);
+                    nodes.len() - 1
+                }
+            };
+        }
+    }
+    nodes
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    String(Box<str>),
+    Double(f64),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    Map(Vec<(Box<str>, Value)>),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            Self::String(s) => {
+                control(buf, 2, s.len());
+                buf.extend_from_slice(s.as_bytes());
+            }
+            Self::Double(x) => {
+                control(buf, 3, 8);
+                buf.extend_from_slice(&x.to_be_bytes());
+            }
+            Self::U16(x) => uint(buf, 5, (*x).into()),
+            Self::U32(x) => uint(buf, 6, (*x).into()),
+            Self::U64(x) => uint(buf, 9, *x),
+            Self::Map(pairs) => {
+                control(buf, 7, pairs.len());
+                for (k, v) in pairs {
+                    Self::String(k.clone()).encode(buf);
+                    v.encode(buf);
+                }
+            }
+            Self::Array(values) => {
+                control(buf, 11, values.len());
+                for v in values {
+                    v.encode(buf);
+                }
+            }
+        }
+    }
+}
+
+/// Without leading zero bytes.
+fn uint(buf: &mut Vec<u8>, type_: u8, n: u64) {
+    let bytes = n.to_be_bytes();
+    let bytes = &bytes[(n.leading_zeros() / 8) as usize..];
+    control(buf, type_, bytes.len());
+    buf.extend_from_slice(bytes);
+}
+
+fn control(buf: &mut Vec<u8>, type_: u8, size: usize) {
+    debug_assert!(size < 65_821 + (1 << 24));
+    let type_bits = if type_ <= 7 { type_ << 5 } else { 0 };
+    let size_bits = match size {
+        0..29 => size as u8,
+        29..285 => 29,
+        285..65_821 => 30,
+        _ => 31,
+    };
+    buf.push(type_bits | size_bits);
+    if type_ > 7 {
+        buf.push(type_ - 7);
+    }
+    match size {
+        0..29 => {}
+        29..285 => buf.push((size - 29) as u8),
+        285..65_821 => buf.extend_from_slice(&((size - 285) as u16).to_be_bytes()),
+        _ => buf.extend_from_slice(&((size - 65_821) as u32).to_be_bytes()[1..]),
+    }
+}
+
+/// GeoLite2-City. Fields not in `fields` are left out, not written from their defaults.
+fn city(json: &JsonResponseBodySuccessJson, lang: &str) -> Value {
+    let present = |field: &str| json.present.contains(field);
+    let names = |field: &str, name: &str| {
+        (present(field) && !name.is_empty()).then(|| {
+            (
+                "names".into(),
+                Value::Map(vec![(lang.into(), Value::String(name.into()))]),
+            )
+        })
+    };
+    let string = |field: &str, k: &str, v: &str| {
+        (present(field) && !v.is_empty()).then(|| (k.into(), Value::String(v.into())))
+    };
+    let object = |pairs: Vec<Option<(Box<str>, Value)>>| {
+        let pairs = pairs.into_iter().flatten().collect::<Vec<_>>();
+        (!pairs.is_empty()).then_some(Value::Map(pairs))
+    };
+
+    let mut map = vec![];
+    if let Some(x) = object(vec![names("city", &json.city)]) {
+        map.push(("city".into(), x));
+    }
+    if let Some(x) = object(vec![
+        string("continentCode", "code", &json.continent_code.to_string()),
+        names("continent", &json.continent),
+    ]) {
+        map.push(("continent".into(), x));
+    }
+    if let Some(x) = object(vec![
+        string("countryCode", "iso_code", &json.country_code.to_string()),
+        names("country", &json.country),
+    ]) {
+        map.push(("country".into(), x));
+    }
+    if let Some(point) = json.geo_point() {
+        map.push((
+            "location".into(),
+            Value::Map(
+                [
+                    Some(("latitude".into(), Value::Double(point.lat))),
+                    Some(("longitude".into(), Value::Double(point.lon))),
+                    string("timezone", "time_zone", json.timezone.name()),
+                ]
+                .into_iter()
+                .flatten()
+                .collect(),
+            ),
+        ));
+    }
+    if let Some(x) = object(vec![string("zip", "code", &json.zip)]) {
+        map.push(("postal".into(), x));
+    }
+    if let Some(x) = object(vec![
+        string("region", "iso_code", &json.region),
+        names("regionName", &json.region_name),
+    ]) {
+        map.push(("subdivisions".into(), Value::Array(vec![x])));
+    }
+    if present("as")
+        && let Some(x) = json.autonomous_system()
+    {
+        map.push(("autonomous_system_number".into(), Value::U32(x.number.0)));
+        map.push((
+            "autonomous_system_organization".into(),
+            Value::String(x.name),
+        ));
+    }
+
+    Value::Map(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{collections::BTreeMap, net::IpAddr};
+
+    use maxminddb::{Reader, geoip2};
+
+    use crate::endpoints::json::JsonResponseBodyJson;
+
+    const FULL_FIELDS: &str =
+        include_str!("../tests/response_body_json_files/json_full_fields.json");
+    const DEFAULT_FIELDS: &str =
+        include_str!("../tests/response_body_json_files/json_default.json");
+
+    fn record(s: &str) -> Box<JsonResponseBodySuccessJson> {
+        match serde_json::from_str::<JsonResponseBodyJson>(s).unwrap() {
+            JsonResponseBodyJson::Success(x) => x,
+            JsonResponseBodyJson::Fail(x) => panic!("{:?}", x),
+        }
+    }
+
+    fn name<'a>(names: &Option<BTreeMap<&str, &'a str>>) -> &'a str {
+        names.as_ref().unwrap()["en"]
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_control() {
+        for (type_, size, bytes) in [
+            (2, 3, &[0x43][..]),
+            (2, 29, &[0x5D, 0x00]),
+            (2, 300, &[0x5E, 0x00, 0x0F]),
+            (2, 65_821, &[0x5F, 0x00, 0x00, 0x00]),
+            (9, 8, &[0x08, 0x02]),
+            (11, 1, &[0x01, 0x04]),
+        ] {
+            let mut buf = vec![];
+            control(&mut buf, type_, size);
+            assert_eq!(buf, bytes, "{} {}", type_, size);
+        }
+
+        let mut buf = vec![];
+        uint(&mut buf, 6, 5769);
+        assert_eq!(buf, [0xC2, 0x16, 0x89]);
+        let mut buf = vec![];
+        uint(&mut buf, 6, 0);
+        assert_eq!(buf, [0xC0]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let full = record(FULL_FIELDS);
+        let google = record(
+            r#"{"status":"success","country":"United States","countryCode":"US","as":"AS15169 Google LLC","query":"2001:4860:4860::8888"}"#,
+        );
+
+        let mut writer = MmdbWriter::new().build_epoch(1_700_000_000);
+        writer.insert(ip("24.48.0.1"), &full);
+        writer.insert("2001:4860::/32".parse::<Prefix>().unwrap(), &google);
+        writer.insert("10.0.0.0/8".parse::<Prefix>().unwrap(), &google);
+        // More specific, inserted first.
+        writer.insert(ip("10.1.2.3"), &full);
+        writer.insert("10.2.0.1-10.2.0.6".parse::<Prefix>().unwrap(), &full);
+        assert_eq!(writer.len(), 8);
+
+        let mut buf = vec![];
+        writer.write(&mut buf).unwrap();
+        let reader = Reader::from_source(buf).unwrap();
+
+        assert_eq!(reader.metadata.database_type, DATABASE_TYPE_DEFAULT);
+        assert_eq!(reader.metadata.ip_version, 6);
+        assert_eq!(reader.metadata.languages, ["en"]);
+        assert_eq!(reader.metadata.build_epoch, 1_700_000_000);
+        assert_eq!(reader.metadata.record_size, 24);
+
+        let city: geoip2::City = reader.lookup(ip("24.48.0.1")).unwrap();
+        assert_eq!(name(&city.city.unwrap().names), full.city.as_ref());
+        assert_eq!(city.continent.unwrap().code, Some("NA"));
+        let country = city.country.unwrap();
+        assert_eq!(country.iso_code, Some("CA"));
+        assert_eq!(name(&country.names), "Canada");
+        let location = city.location.unwrap();
+        assert_eq!(location.latitude, Some(full.lat));
+        assert_eq!(location.longitude, Some(full.lon));
+        assert_eq!(location.time_zone, Some(full.timezone.name()));
+        assert_eq!(city.postal.unwrap().code, Some(full.zip.as_ref()));
+        let subdivisions = city.subdivisions.unwrap();
+        assert_eq!(subdivisions[0].iso_code, Some("QC"));
+        assert_eq!(name(&subdivisions[0].names), full.region_name.as_ref());
+
+        let asn: geoip2::Asn = reader.lookup(ip("24.48.0.1")).unwrap();
+        assert_eq!(asn.autonomous_system_number, Some(5769));
+        assert_eq!(
+            asn.autonomous_system_organization,
+            Some("Videotron Telecom Ltee")
+        );
+
+        let (city, prefix_len) = reader
+            .lookup_prefix::<geoip2::City>(ip("2001:4860:4860::8888"))
+            .unwrap();
+        assert_eq!(prefix_len, 32);
+        assert_eq!(city.country.unwrap().iso_code, Some("US"));
+        // Not in `fields`.
+        assert!(city.location.is_none());
+        assert!(city.subdivisions.is_none());
+
+        let country_code = |s: &str| {
+            reader
+                .lookup::<geoip2::City>(ip(s))
+                .ok()
+                .and_then(|x| x.country?.iso_code)
+        };
+        assert_eq!(country_code("10.1.2.3"), Some("CA"));
+        assert_eq!(country_code("10.1.2.4"), Some("US"));
+        assert_eq!(country_code("10.255.255.255"), Some("US"));
+        assert_eq!(country_code("10.2.0.1"), Some("CA"));
+        assert_eq!(country_code("10.2.0.6"), Some("CA"));
+        assert_eq!(country_code("10.2.0.7"), Some("US"));
+        assert_eq!(country_code("24.48.0.2"), None);
+        assert_eq!(country_code("2001:4861::"), None);
+    }
+
+    #[test]
+    fn test_round_trip_without_fields() {
+        let default = record(DEFAULT_FIELDS);
+        let located = record(
+            r#"{"status":"success","countryCode":"US","lat":37.4223,"lon":-122.085,"query":"8.8.8.8"}"#,
+        );
+
+        let mut writer = MmdbWriter::new().build_epoch(0);
+        writer.insert(ip("24.48.0.1"), &default);
+        writer.insert(ip("8.8.8.8"), &located);
+        let reader = Reader::from_source(writer.to_vec()).unwrap();
+
+        let city: geoip2::City = reader.lookup(ip("24.48.0.1")).unwrap();
+        // `continentCode` and `continent` are not in the default fields.
+        assert!(city.continent.is_none());
+        assert_eq!(city.country.unwrap().iso_code, Some("CA"));
+        assert_eq!(city.location.unwrap().time_zone, Some("America/Toronto"));
+
+        let city: geoip2::City = reader.lookup(ip("8.8.8.8")).unwrap();
+        assert!(city.continent.is_none());
+        let country = city.country.unwrap();
+        assert_eq!(country.iso_code, Some("US"));
+        assert!(country.names.is_none());
+        let location = city.location.unwrap();
+        assert_eq!(location.latitude, Some(37.4223));
+        assert!(location.time_zone.is_none());
+        assert!(city.subdivisions.is_none());
+    }
+
+    #[test]
+    fn test_record_size() {
+        let full = record(FULL_FIELDS);
+        let mut writer = MmdbWriter::new().build_epoch(0);
+        // A distinct record per address, the data section outgrows 24 bits.
+        let ip = |i: u32| IpAddr::from((0x0100_0000 + i).to_be_bytes());
+        for i in 0..16_000 {
+            let mut json = full.clone();
+            json.zip = format!("{:0>1024}", i).into();
+            writer.insert(ip(i), &json);
+        }
+        let reader = Reader::from_source(writer.to_vec()).unwrap();
+        assert_eq!(reader.metadata.record_size, 28);
+        for i in [0, 256, 15_999] {
+            let city: geoip2::City = reader.lookup(ip(i)).unwrap();
+            assert_eq!(
+                city.postal.unwrap().code.map(|x| x.trim_start_matches('0')),
+                Some(i.to_string().trim_start_matches('0'))
+            );
+        }
+
+        let reader = Reader::from_source(MmdbWriter::new().to_vec()).unwrap();
+        assert!(
+            reader
+                .lookup::<geoip2::City>(self::ip("24.48.0.1"))
+                .is_err()
+        );
+    }
+}
@@ -1,9 +1,11 @@
 //! https://ip-api.com/docs/api:json
 
+use serde::{Deserialize, Serialize};
+
 pub const RESPONSE_HEADER_KEY_X_RL: &str = "X-Rl";
 pub const RESPONSE_HEADER_KEY_X_TTL: &str = "X-Ttl";
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq, Eq)]
 pub struct RateLimit {
     pub remaining: Option<usize>,
     pub seconds_until_reset: Option<usize>,
@@ -0,0 +1,311 @@
+//! Geo access control, evaluated against a lookup result.
+//!
+//! Rules are checked in order and the first match decides, `default_action` applies when none match.
+//! Within a rule all set conditions must hold, an empty list or unset flag matches anything.
+//! A condition on a field that was not in `fields` never holds, e.g. `continents` without
+//! `continentCode`.
+
+use continent_code::ContinentCode;
+use country_code::CountryCode;
+use serde::{Deserialize, Serialize};
+
+use crate::{endpoints::json::JsonResponseBodySuccessJson, types::asn::Asn};
+
+//
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Policy {
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    pub default_action: Action,
+}
+
+impl Policy {
+    pub fn new(default_action: Action) -> Self {
+        Self {
+            rules: vec![],
+            default_action,
+        }
+    }
+
+    pub fn rule(mut self, rule: Rule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    pub fn evaluate(&self, json: &JsonResponseBodySuccessJson) -> Decision<'_> {
+        match self
+            .rules
+            .iter()
+            .enumerate()
+            .find(|(_, rule)| rule.matches(json))
+        {
+            Some((index, rule)) => Decision {
+                action: rule.action,
+                rule: Some((index, rule)),
+            },
+            None => Decision {
+                action: self.default_action,
+                rule: None,
+            },
+        }
+    }
+}
+
+//
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Allow,
+    Deny,
+}
+
+//
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    /// For audit logs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<Box<str>>,
+    pub action: Action,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub countries: Vec<CountryCode>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub continents: Vec<ContinentCode>,
+    /// ISO 3166-2 codes, e.g. "CA-QC", built from `countryCode` and `region`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub regions: Vec<Box<str>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub asns: Vec<Asn>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hosting: Option<bool>,
+}
+
+impl Rule {
+    /// Matches anything until conditions are added.
+    pub fn new(action: Action) -> Self {
+        Self {
+            name: None,
+            action,
+            countries: vec![],
+            continents: vec![],
+            regions: vec![],
+            asns: vec![],
+            proxy: None,
+            hosting: None,
+        }
+    }
+
+    pub fn name(mut self, name: impl AsRef<str>) -> Self {
+        self.name = Some(name.as_ref().into());
+        self
+    }
+
+    pub fn countries(mut self, countries: impl IntoIterator<Item = CountryCode>) -> Self {
+        self.countries = countries.into_iter().collect();
+        self
+    }
+
+    pub fn continents(mut self, continents: impl IntoIterator<Item = ContinentCode>) -> Self {
+        self.continents = continents.into_iter().collect();
+        self
+    }
+
+    pub fn regions<S: AsRef<str>>(mut self, regions: impl IntoIterator<Item = S>) -> Self {
+        self.regions = regions.into_iter().map(|x| x.as_ref().into()).collect();
+        self
+    }
+
+    pub fn asns(mut self, asns: impl IntoIterator<Item = Asn>) -> Self {
+        self.asns = asns.into_iter().collect();
+        self
+    }
+
+    pub fn proxy(mut self, proxy: bool) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    pub fn hosting(mut self, hosting: bool) -> Self {
+        self.hosting = Some(hosting);
+        self
+    }
+
+    pub fn matches(&self, json: &JsonResponseBodySuccessJson) -> bool {
+        let present = |name| json.present.contains(name);
+        let country_code = present("countryCode").then_some(&json.country_code);
+        let continent_code = present("continentCode").then_some(&json.continent_code);
+
+        if !self.countries.is_empty() && !country_code.is_some_and(|x| self.countries.contains(x)) {
+            return false;
+        }
+        if !self.continents.is_empty()
+            && !continent_code.is_some_and(|x| self.continents.contains(x))
+        {
+            return false;
+        }
+        if !self.regions.is_empty() {
+            if country_code.is_none() || !present("region") {
+                return false;
+            }
+            let region = format!("{}-{}", json.country_code, json.region);
+            if !self.regions.iter().any(|x| x.eq_ignore_ascii_case(&region)) {
+                return false;
+            }
+        }
+        if !self.asns.is_empty() && !json.asn().is_some_and(|x| self.asns.contains(&x)) {
+            return false;
+        }
+        if self
+            .proxy
+            .is_some_and(|x| !present("proxy") || x != json.proxy)
+        {
+            return false;
+        }
+        if self
+            .hosting
+            .is_some_and(|x| !present("hosting") || x != json.hosting)
+        {
+            return false;
+        }
+        true
+    }
+}
+
+//
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decision<'a> {
+    pub action: Action,
+    /// Index and rule that matched, `None` for `default_action`.
+    pub rule: Option<(usize, &'a Rule)>,
+}
+
+impl Decision<'_> {
+    pub fn is_allowed(&self) -> bool {
+        self.action == Action::Allow
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn success_json() -> JsonResponseBodySuccessJson {
+        serde_json::from_str(include_str!(
+            "../tests/response_body_json_files/json_full_fields.json"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let mut json = success_json();
+
+        let policy = Policy::new(Action::Deny)
+            .rule(Rule::new(Action::Deny).name("no-proxy").proxy(true))
+            .rule(
+                Rule::new(Action::Allow)
+                    .name("north-america")
+                    .continents([ContinentCode::NA]),
+            );
+
+        let decision = policy.evaluate(&json);
+        assert!(decision.is_allowed());
+        let (index, rule) = decision.rule.unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(rule.name.as_deref(), Some("north-america"));
+
+        json.proxy = true;
+        let decision = policy.evaluate(&json);
+        assert_eq!(decision.action, Action::Deny);
+        assert_eq!(decision.rule.unwrap().0, 0);
+
+        json.proxy = false;
+        json.continent_code = ContinentCode::EU;
+        let decision = policy.evaluate(&json);
+        assert_eq!(decision.action, Action::Deny);
+        assert!(decision.rule.is_none());
+    }
+
+    #[test]
+    fn test_rule_matches() {
+        let json = success_json();
+
+        assert!(Rule::new(Action::Deny).matches(&json));
+        assert!(
+            Rule::new(Action::Deny)
+                .countries([CountryCode::US, CountryCode::CA])
+                .regions(["ca-qc"])
+                .asns([Asn(5769)])
+                .hosting(false)
+                .matches(&json)
+        );
+        assert!(!Rule::new(Action::Deny).regions(["CA-ON"]).matches(&json));
+        assert!(!Rule::new(Action::Deny).asns([Asn(1)]).matches(&json));
+        assert!(!Rule::new(Action::Deny).hosting(true).matches(&json));
+    }
+
+    #[test]
+    fn test_rule_matches_without_fields() {
+        let json: JsonResponseBodySuccessJson = serde_json::from_str(include_str!(
+            "../tests/response_body_json_files/json_default.json"
+        ))
+        .unwrap();
+
+        // `continentCode` defaults to `AS` when not in `fields`.
+        let policy = Policy::new(Action::Allow)
+            .rule(Rule::new(Action::Deny).continents([ContinentCode::AS]));
+        assert!(policy.evaluate(&json).is_allowed());
+        let policy = Policy::new(Action::Deny)
+            .rule(Rule::new(Action::Allow).continents([ContinentCode::NA]));
+        assert!(!policy.evaluate(&json).is_allowed());
+
+        assert!(!Rule::new(Action::Deny).hosting(false).matches(&json));
+        assert!(
+            Rule::new(Action::Deny)
+                .countries([CountryCode::CA])
+                .regions(["CA-QC"])
+                .asns([Asn(5769)])
+                .matches(&json)
+        );
+    }
+
+    #[test]
+    fn test_policy_de() {
+        let policy: Policy = serde_json::from_str(
+            r#"
+            {
+                "rules": [
+                    {"name": "block-hosting", "action": "deny", "hosting": true},
+                    {"action": "allow", "countries": ["CA", "US"], "asns": ["AS5769"]}
+                ],
+                "default_action": "deny"
+            }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            policy,
+            Policy::new(Action::Deny)
+                .rule(Rule::new(Action::Deny).name("block-hosting").hosting(true))
+                .rule(
+                    Rule::new(Action::Allow)
+                        .countries([CountryCode::CA, CountryCode::US])
+                        .asns([Asn(5769)])
+                )
+        );
+        assert!(policy.evaluate(&success_json()).is_allowed());
+
+        let policy_str = serde_json::to_string(&policy).unwrap();
+        assert_eq!(serde_json::from_str::<Policy>(&policy_str).unwrap(), policy);
+
+        assert!(
+            serde_json::from_str::<Policy>(
+                r#"{"rules":[{"action":"deny","country":["CA"]}],"default_action":"allow"}"#
+            )
+            .is_err()
+        );
+    }
+}
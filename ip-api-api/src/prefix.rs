@@ -0,0 +1,501 @@
+//! Geolocate prefixes by sampling addresses from CIDR blocks or ranges.
+//!
+//! The samples are looked up, e.g. with `client::bulk::lookup_prefixes`, and summarized per
+//! prefix as the distribution of the countries and ASNs observed.
+
+use core::{cmp::Reverse, fmt, str::FromStr};
+use std::{
+    collections::{BTreeSet, HashMap},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+};
+
+use country_code::CountryCode;
+use ipnet::{IpNet, Ipv4Subnets, Ipv6Subnets};
+use rand::{Rng, RngExt as _};
+use serde::{Serialize, Serializer};
+
+use crate::{endpoints::json::JsonResponseBodyJson, types::asn::Asn};
+
+/// Per prefix, more is an error rather than a huge `Vec`.
+pub const SAMPLE_MAX: usize = 1 << 16;
+
+//
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Prefix {
+    Net(IpNet),
+    /// Inclusive, see `Prefix::range`.
+    Range(IpAddr, IpAddr),
+}
+
+impl Prefix {
+    pub fn range(start: IpAddr, end: IpAddr) -> Result<Self, PrefixParseError> {
+        if start.is_ipv6() != end.is_ipv6() || to_u128(start) > to_u128(end) {
+            return Err(PrefixParseError::RangeInvalid(start, end));
+        }
+        Ok(Self::Range(start, end))
+    }
+
+    pub fn first(&self) -> IpAddr {
+        match self {
+            Self::Net(net) => net.network(),
+            Self::Range(start, _) => *start,
+        }
+    }
+
+    pub fn last(&self) -> IpAddr {
+        match self {
+            Self::Net(net) => net.broadcast(),
+            Self::Range(_, end) => *end,
+        }
+    }
+
+    pub fn is_ipv6(&self) -> bool {
+        self.first().is_ipv6()
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        ip.is_ipv6() == self.is_ipv6()
+            && (to_u128(self.first())..=to_u128(self.last())).contains(&to_u128(*ip))
+    }
+
+    /// The fewest CIDR blocks covering exactly this prefix.
+    pub fn nets(&self) -> Vec<IpNet> {
+        match *self {
+            Self::Net(net) => vec![net],
+            Self::Range(IpAddr::V4(start), IpAddr::V4(end)) => {
+                Ipv4Subnets::new(start, end, 0).map(Into::into).collect()
+            }
+            Self::Range(IpAddr::V6(start), IpAddr::V6(end)) => {
+                Ipv6Subnets::new(start, end, 0).map(Into::into).collect()
+            }
+            // Mixed families, rejected by `Prefix::range`.
+            Self::Range(..) => vec![],
+        }
+    }
+
+    /// Number of addresses, saturating for `::/0`.
+    pub fn size(&self) -> u128 {
+        (to_u128(self.last()) - to_u128(self.first())).saturating_add(1)
+    }
+
+    /// Ascending and distinct.
+    pub fn sample(
+        &self,
+        sampling: Sampling,
+        rng: &mut impl Rng,
+    ) -> Result<Vec<IpAddr>, SampleError> {
+        let v6 = self.is_ipv6();
+        let (start, end) = (to_u128(self.first()), to_u128(self.last()));
+
+        let ips = match sampling {
+            Sampling::FirstHost => vec![first_host(start, end, matches!(self, Self::Net(_)))],
+            Sampling::EverySubnet { v4, v6: len_v6 } => {
+                let (width, len) = if v6 { (128, len_v6) } else { (32, v4) };
+                let Some(block) = 1u128.checked_shl(width - u32::from(len).min(width)) else {
+                    // `/0`, a single subnet.
+                    return Ok(vec![to_ip(first_host(start, end, true), v6)]);
+                };
+
+                let count = (end / block - start / block).checked_add(1);
+                if count.is_none_or(|x| x > SAMPLE_MAX as u128) {
+                    return Err(SampleError::TooMany(count.unwrap_or(u128::MAX)));
+                }
+                (start / block..=end / block)
+                    .map(|i| {
+                        let (s, e) = (i * block, i * block + (block - 1));
+                        first_host(s.max(start), e.min(end), s >= start)
+                    })
+                    .collect()
+            }
+            Sampling::Random(n) => {
+                if n > SAMPLE_MAX {
+                    return Err(SampleError::TooMany(n as u128));
+                }
+                let size = self.size();
+                if n as u128 >= size {
+                    (start..=end).collect()
+                } else if n as u128 * 2 >= size {
+                    // Dense, a partial shuffle of every address.
+                    let mut all = (start..=end).collect::<Vec<_>>();
+                    for i in 0..n {
+                        let j = rng.random_range(i..all.len());
+                        all.swap(i, j);
+                    }
+                    all.truncate(n);
+                    all.sort_unstable();
+                    all
+                } else {
+                    let mut set = BTreeSet::new();
+                    while set.len() < n {
+                        set.insert(rng.random_range(start..=end));
+                    }
+                    set.into_iter().collect()
+                }
+            }
+        };
+
+        Ok(ips.into_iter().map(|x| to_ip(x, v6)).collect())
+    }
+}
+
+/// Skips the network address, e.g. `1.2.3.1` for `1.2.3.0/24`, unless it is all there is.
+fn first_host(start: u128, end: u128, aligned: bool) -> u128 {
+    if aligned && end - start >= 2 {
+        start + 1
+    } else {
+        start
+    }
+}
+
+fn to_u128(ip: IpAddr) -> u128 {
+    match ip {
+        IpAddr::V4(ip) => u32::from(ip).into(),
+        IpAddr::V6(ip) => ip.into(),
+    }
+}
+
+fn to_ip(n: u128, v6: bool) -> IpAddr {
+    if v6 {
+        Ipv6Addr::from(n).into()
+    } else {
+        Ipv4Addr::from(n as u32).into()
+    }
+}
+
+impl From<IpAddr> for Prefix {
+    fn from(ip: IpAddr) -> Self {
+        Self::Net(ip.into())
+    }
+}
+
+impl From<IpNet> for Prefix {
+    fn from(net: IpNet) -> Self {
+        Self::Net(net.trunc())
+    }
+}
+
+impl FromStr for Prefix {
+    type Err = PrefixParseError;
+
+    /// Accepts "1.2.3.0/24", "1.2.3.4-1.2.3.9" and "1.2.3.4".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = || PrefixParseError::Invalid(s.into());
+        if s.contains('/') {
+            s.parse::<IpNet>().map(Into::into).map_err(|_| invalid())
+        } else if let Some((start, end)) = s.split_once('-') {
+            let start = start.trim().parse().map_err(|_| invalid())?;
+            let end = end.trim().parse().map_err(|_| invalid())?;
+            Self::range(start, end)
+        } else {
+            s.parse::<IpAddr>()
+                .map(|x| Self::Net(x.into()))
+                .map_err(|_| invalid())
+        }
+    }
+}
+
+impl fmt::Display for Prefix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Net(net) => write!(f, "{}", net),
+            Self::Range(start, end) => write!(f, "{}-{}", start, end),
+        }
+    }
+}
+
+impl Serialize for Prefix {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+//
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PrefixParseError {
+    Invalid(Box<str>),
+    /// Mixed families or start after end.
+    RangeInvalid(IpAddr, IpAddr),
+}
+
+impl fmt::Display for PrefixParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for PrefixParseError {}
+
+//
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Sampling {
+    FirstHost,
+    /// The first host of every subnet of these lengths, see `Sampling::EVERY_24`.
+    EverySubnet {
+        v4: u8,
+        v6: u8,
+    },
+    /// `n` distinct addresses, every address when the prefix has no more than `n`.
+    Random(usize),
+}
+
+impl Sampling {
+    /// Every /24, or /48 for IPv6.
+    pub const EVERY_24: Self = Self::EverySubnet { v4: 24, v6: 48 };
+}
+
+//
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SampleError {
+    /// More than `SAMPLE_MAX` addresses, saturating, e.g. every /128 in `::/0`.
+    TooMany(u128),
+}
+
+impl fmt::Display for SampleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for SampleError {}
+
+//
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PrefixSummary {
+    pub prefix: Prefix,
+    pub sampled: usize,
+    /// Fail results, e.g. reserved ranges, and samples without a result.
+    pub failed: usize,
+    /// Most observed first.
+    pub countries: Vec<(CountryCode, usize)>,
+    /// Most observed first.
+    pub asns: Vec<(Asn, usize)>,
+}
+
+impl PrefixSummary {
+    pub fn new<'a>(
+        prefix: Prefix,
+        results: impl IntoIterator<Item = Option<&'a JsonResponseBodyJson>>,
+    ) -> Self {
+        let mut sampled = 0;
+        let mut failed = 0;
+        let mut countries = HashMap::new();
+        let mut asns = HashMap::new();
+        for result in results {
+            sampled += 1;
+            match result.and_then(JsonResponseBodyJson::as_success) {
+                Some(x) => {
+                    // Not in `fields`, rather than the default.
+                    if x.present.contains("countryCode") {
+                        *countries.entry(x.country_code.clone()).or_insert(0) += 1;
+                    }
+                    if x.present.contains("as")
+                        && let Some(asn) = x.asn()
+                    {
+                        *asns.entry(asn).or_insert(0) += 1;
+                    }
+                }
+                None => failed += 1,
+            }
+        }
+
+        Self {
+            prefix,
+            sampled,
+            failed,
+            countries: distribution(countries),
+            asns: distribution(asns),
+        }
+    }
+
+    /// The most observed country and its share of the succeeded samples.
+    pub fn top_country(&self) -> Option<(&CountryCode, f64)> {
+        self.top(&self.countries)
+    }
+
+    /// The most observed ASN and its share of the succeeded samples.
+    pub fn top_asn(&self) -> Option<(&Asn, f64)> {
+        self.top(&self.asns)
+    }
+
+    fn top<'a, K>(&self, distribution: &'a [(K, usize)]) -> Option<(&'a K, f64)> {
+        let (k, n) = distribution.first()?;
+        Some((k, *n as f64 / (self.sampled - self.failed) as f64))
+    }
+}
+
+/// Ties in key order, so that summaries are stable.
+fn distribution<K: fmt::Display>(counts: HashMap<K, usize>) -> Vec<(K, usize)> {
+    let mut distribution = counts.into_iter().collect::<Vec<_>>();
+    distribution.sort_by_cached_key(|(k, n)| (Reverse(*n), k.to_string()));
+    distribution
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::{SeedableRng as _, rngs::StdRng};
+
+    fn ips(s: &[&str]) -> Vec<IpAddr> {
+        s.iter().map(|x| x.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let prefix: Prefix = "24.48.0.7/22".parse().unwrap();
+        assert_eq!(prefix.to_string(), "24.48.0.0/22");
+        assert_eq!(prefix.size(), 1024);
+        assert!(prefix.contains(&"24.48.3.255".parse().unwrap()));
+        assert!(!prefix.contains(&"24.48.4.0".parse().unwrap()));
+        assert!(!prefix.contains(&"::1".parse().unwrap()));
+
+        let prefix: Prefix = "24.48.0.1 - 24.48.0.9".parse().unwrap();
+        assert_eq!(prefix.to_string(), "24.48.0.1-24.48.0.9");
+        assert_eq!(prefix.size(), 9);
+
+        assert_eq!("2001:db8::1".parse::<Prefix>().unwrap().size(), 1);
+        assert_eq!("::/0".parse::<Prefix>().unwrap().size(), u128::MAX);
+
+        assert_eq!(
+            "24.48.0.1-24.48.0.9"
+                .parse::<Prefix>()
+                .unwrap()
+                .nets()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "24.48.0.1/32",
+                "24.48.0.2/31",
+                "24.48.0.4/30",
+                "24.48.0.8/31"
+            ]
+        );
+        assert_eq!(
+            Prefix::from("2001:db8::1".parse::<IpAddr>().unwrap()).nets(),
+            ["2001:db8::1/128".parse::<IpNet>().unwrap()]
+        );
+
+        assert!("24.48.0.9-24.48.0.1".parse::<Prefix>().is_err());
+        assert!("24.48.0.1-::1".parse::<Prefix>().is_err());
+        assert!("example.com".parse::<Prefix>().is_err());
+    }
+
+    #[test]
+    fn test_sample() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let sample = |s: &str, sampling, rng: &mut StdRng| {
+            s.parse::<Prefix>().unwrap().sample(sampling, rng)
+        };
+
+        assert_eq!(
+            sample("24.48.0.0/22", Sampling::FirstHost, &mut rng).unwrap(),
+            ips(&["24.48.0.1"])
+        );
+        assert_eq!(
+            sample("24.48.0.0/31", Sampling::FirstHost, &mut rng).unwrap(),
+            ips(&["24.48.0.0"])
+        );
+        assert_eq!(
+            sample("24.48.0.0-24.48.0.9", Sampling::FirstHost, &mut rng).unwrap(),
+            ips(&["24.48.0.0"])
+        );
+
+        assert_eq!(
+            sample("24.48.0.0/22", Sampling::EVERY_24, &mut rng).unwrap(),
+            ips(&["24.48.0.1", "24.48.1.1", "24.48.2.1", "24.48.3.1"])
+        );
+        assert_eq!(
+            sample("24.48.0.128-24.48.2.0", Sampling::EVERY_24, &mut rng).unwrap(),
+            ips(&["24.48.0.128", "24.48.1.1", "24.48.2.0"])
+        );
+        assert_eq!(
+            sample("2001:db8::/47", Sampling::EVERY_24, &mut rng).unwrap(),
+            ips(&["2001:db8::1", "2001:db8:1::1"])
+        );
+        assert_eq!(
+            sample("::/0", Sampling::EverySubnet { v4: 0, v6: 0 }, &mut rng).unwrap(),
+            ips(&["::1"])
+        );
+        assert_eq!(
+            sample("10.0.0.0/8", Sampling::EVERY_24, &mut rng)
+                .unwrap()
+                .len(),
+            SAMPLE_MAX
+        );
+        assert_eq!(
+            sample("2001:db8::/16", Sampling::EVERY_24, &mut rng),
+            Err(SampleError::TooMany(1 << 32))
+        );
+        assert_eq!(
+            sample("::/0", Sampling::EverySubnet { v4: 32, v6: 128 }, &mut rng),
+            Err(SampleError::TooMany(u128::MAX))
+        );
+
+        for n in [3, 6, 9] {
+            let ips = sample("24.48.0.0/29", Sampling::Random(n), &mut rng).unwrap();
+            assert_eq!(ips.len(), n.min(8));
+            assert!(ips.windows(2).all(|x| x[0] < x[1]));
+        }
+        let ips = sample("2001:db8::/32", Sampling::Random(100), &mut rng).unwrap();
+        assert_eq!(ips.len(), 100);
+        assert!(
+            ips.iter()
+                .all(|x| "2001:db8::/32".parse::<Prefix>().unwrap().contains(x))
+        );
+    }
+
+    #[test]
+    fn test_summary() {
+        let json = |s: &str| serde_json::from_str::<JsonResponseBodyJson>(s).unwrap();
+        let results = [
+            json(
+                r#"{"status":"success","countryCode":"CA","as":"AS5769 Videotron Telecom Ltee","query":"24.48.0.1"}"#,
+            ),
+            json(
+                r#"{"status":"success","countryCode":"CA","as":"AS5769 Videotron Telecom Ltee","query":"24.48.1.1"}"#,
+            ),
+            json(
+                r#"{"status":"success","countryCode":"US","as":"AS15169 Google LLC","query":"24.48.2.1"}"#,
+            ),
+            json(r#"{"status":"fail","message":"reserved range","query":"24.48.3.1"}"#),
+        ];
+        let summary = PrefixSummary::new(
+            "24.48.0.0/22".parse().unwrap(),
+            results.iter().map(Some).chain([None]),
+        );
+
+        assert_eq!(summary.sampled, 5);
+        assert_eq!(summary.failed, 2);
+        assert_eq!(
+            summary.countries,
+            [(CountryCode::CA, 2), (CountryCode::US, 1)]
+        );
+        assert_eq!(summary.asns, [(Asn(5769), 2), (Asn(15169), 1)]);
+        let (country_code, share) = summary.top_country().unwrap();
+        assert_eq!(*country_code, CountryCode::CA);
+        assert!((share - 2.0 / 3.0).abs() < 1e-9);
+
+        assert_eq!(
+            serde_json::to_string(&summary).unwrap(),
+            r#"{"prefix":"24.48.0.0/22","sampled":5,"failed":2,"countries":[["CA",2],["US",1]],"asns":[["AS5769",2],["AS15169",1]]}"#
+        );
+
+        let empty = PrefixSummary::new("24.48.0.0/22".parse().unwrap(), []);
+        assert!(empty.top_asn().is_none());
+
+        let without_fields = json(r#"{"status":"success","country":"Canada","query":"24.48.0.1"}"#);
+        let summary = PrefixSummary::new(
+            "24.48.0.0/22".parse().unwrap(),
+            [Some(&without_fields), Some(&without_fields)],
+        );
+        assert_eq!(summary.failed, 0);
+        assert!(summary.countries.is_empty());
+        assert!(summary.asns.is_empty());
+        assert!(summary.top_country().is_none());
+    }
+}
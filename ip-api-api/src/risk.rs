@@ -0,0 +1,242 @@
+//! Risk scoring from `proxy`, `hosting`, `mobile`, `as` and `countryCode`.
+//!
+//! Needs those fields in `fields`, a missing flag counts as `false` and a missing country is in
+//! neither list.
+
+use country_code::CountryCode;
+use serde::{Deserialize, Serialize};
+
+use crate::{endpoints::json::JsonResponseBodySuccessJson, types::asn::Asn};
+
+pub const SCORE_MAX: u32 = 100;
+
+//
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct RiskConfig {
+    pub proxy_weight: u32,
+    pub hosting_weight: u32,
+    pub mobile_weight: u32,
+    /// Added once for a match in `denied_asns`.
+    pub denied_asn_weight: u32,
+    pub denied_asns: Vec<Asn>,
+    /// Always scored `0`, e.g. your own networks or known partners.
+    pub allowed_asns: Vec<Asn>,
+    /// Added once for a match in `denied_countries`, or a miss in non-empty `allowed_countries`.
+    pub country_weight: u32,
+    pub denied_countries: Vec<CountryCode>,
+    pub allowed_countries: Vec<CountryCode>,
+}
+
+impl Default for RiskConfig {
+    fn default() -> Self {
+        Self {
+            proxy_weight: 60,
+            hosting_weight: 40,
+            mobile_weight: 10,
+            denied_asn_weight: 80,
+            denied_asns: vec![],
+            allowed_asns: vec![],
+            country_weight: 50,
+            denied_countries: vec![],
+            allowed_countries: vec![],
+        }
+    }
+}
+
+impl RiskConfig {
+    pub fn assess(&self, json: &JsonResponseBodySuccessJson) -> RiskAssessment {
+        let asn = json.asn();
+
+        if let Some(asn) = asn
+            && self.allowed_asns.contains(&asn)
+        {
+            return RiskAssessment {
+                score: 0,
+                reasons: vec![RiskReason::AsnAllowed(asn)],
+            };
+        }
+
+        let mut reasons = vec![];
+        if json.proxy {
+            reasons.push(RiskReason::Proxy);
+        }
+        if json.hosting {
+            reasons.push(RiskReason::Hosting);
+        }
+        if json.mobile {
+            reasons.push(RiskReason::Mobile);
+        }
+        if let Some(asn) = asn
+            && self.denied_asns.contains(&asn)
+        {
+            reasons.push(RiskReason::AsnDenied(asn));
+        }
+        if json.present.contains("countryCode") {
+            if self.denied_countries.contains(&json.country_code) {
+                reasons.push(RiskReason::CountryDenied(json.country_code.clone()));
+            } else if !self.allowed_countries.is_empty()
+                && !self.allowed_countries.contains(&json.country_code)
+            {
+                reasons.push(RiskReason::CountryNotAllowed(json.country_code.clone()));
+            }
+        }
+
+        let score = reasons
+            .iter()
+            .map(|x| self.weight(x))
+            .fold(0_u32, u32::saturating_add)
+            .min(SCORE_MAX);
+
+        RiskAssessment { score, reasons }
+    }
+
+    pub fn weight(&self, reason: &RiskReason) -> u32 {
+        match reason {
+            RiskReason::Proxy => self.proxy_weight,
+            RiskReason::Hosting => self.hosting_weight,
+            RiskReason::Mobile => self.mobile_weight,
+            RiskReason::AsnDenied(_) => self.denied_asn_weight,
+            RiskReason::AsnAllowed(_) => 0,
+            RiskReason::CountryDenied(_) | RiskReason::CountryNotAllowed(_) => self.country_weight,
+        }
+    }
+}
+
+//
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RiskAssessment {
+    /// `0..=SCORE_MAX`
+    pub score: u32,
+    pub reasons: Vec<RiskReason>,
+}
+
+impl RiskAssessment {
+    pub fn exceeds(&self, threshold: u32) -> bool {
+        self.score > threshold
+    }
+}
+
+//
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "reason", content = "value", rename_all = "snake_case")]
+pub enum RiskReason {
+    Proxy,
+    Hosting,
+    Mobile,
+    AsnDenied(Asn),
+    AsnAllowed(Asn),
+    CountryDenied(CountryCode),
+    CountryNotAllowed(CountryCode),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn success_json() -> JsonResponseBodySuccessJson {
+        serde_json::from_str(include_str!(
+            "../tests/response_body_json_files/json_full_fields.json"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_assess() {
+        let mut json = success_json();
+        let config = RiskConfig::default();
+
+        let assessment = config.assess(&json);
+        assert_eq!(assessment.score, 0);
+        assert!(assessment.reasons.is_empty());
+
+        json.proxy = true;
+        json.hosting = true;
+        let assessment = config.assess(&json);
+        assert_eq!(assessment.score, SCORE_MAX);
+        assert_eq!(assessment.reasons, [RiskReason::Proxy, RiskReason::Hosting]);
+        assert!(assessment.exceeds(50));
+
+        json.proxy = false;
+        json.hosting = false;
+        json.mobile = true;
+        assert_eq!(config.assess(&json).score, 10);
+    }
+
+    #[test]
+    fn test_assess_lists() {
+        let json = success_json();
+        let asn = json.asn().unwrap();
+
+        let config = RiskConfig {
+            denied_asns: vec![asn],
+            denied_countries: vec![json.country_code.clone()],
+            ..Default::default()
+        };
+        let assessment = config.assess(&json);
+        assert_eq!(
+            assessment.reasons,
+            [
+                RiskReason::AsnDenied(asn),
+                RiskReason::CountryDenied(json.country_code.clone())
+            ]
+        );
+        assert_eq!(assessment.score, SCORE_MAX);
+
+        let config = RiskConfig {
+            allowed_countries: vec![CountryCode::US],
+            ..Default::default()
+        };
+        assert_eq!(
+            config.assess(&json).reasons,
+            [RiskReason::CountryNotAllowed(json.country_code.clone())]
+        );
+        assert_eq!(config.assess(&json).score, 50);
+
+        let config = RiskConfig {
+            allowed_asns: vec![asn],
+            denied_asns: vec![asn],
+            proxy_weight: 100,
+            ..Default::default()
+        };
+        let assessment = config.assess(&json);
+        assert_eq!(assessment.score, 0);
+        assert_eq!(assessment.reasons, [RiskReason::AsnAllowed(asn)]);
+    }
+
+    #[test]
+    fn test_assess_without_country_code() {
+        let mut value = serde_json::from_str::<serde_json::Value>(include_str!(
+            "../tests/response_body_json_files/json_default.json"
+        ))
+        .unwrap();
+        value.as_object_mut().unwrap().remove("countryCode");
+        let json = serde_json::from_value::<JsonResponseBodySuccessJson>(value).unwrap();
+
+        // The default is not a country.
+        let config = RiskConfig {
+            denied_countries: vec![json.country_code.clone()],
+            ..Default::default()
+        };
+        assert_eq!(config.assess(&json).score, 0);
+        let config = RiskConfig {
+            allowed_countries: vec![CountryCode::US],
+            ..Default::default()
+        };
+        assert!(config.assess(&json).reasons.is_empty());
+    }
+
+    #[test]
+    fn test_config_de() {
+        let config: RiskConfig =
+            serde_json::from_str(r#"{"proxy_weight":90,"denied_asns":["AS13335"]}"#).unwrap();
+        assert_eq!(config.proxy_weight, 90);
+        assert_eq!(config.hosting_weight, 40);
+        assert_eq!(config.denied_asns, [Asn(13335)]);
+
+        assert_eq!(
+            serde_json::to_string(&RiskReason::AsnDenied(Asn(13335))).unwrap(),
+            r#"{"reason":"asn_denied","value":"AS13335"}"#
+        );
+    }
+}
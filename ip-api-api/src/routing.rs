@@ -0,0 +1,235 @@
+//! Pick the closest service region for a client from its lookup result.
+//!
+//! Regions are ranked by great-circle distance, minus a bonus when the client
+//! is in one of the region's preferred countries or continents.
+//! Without `lat`/`lon`, only the affinity counts, ties keep the list order.
+
+use continent_code::ContinentCode;
+use country_code::CountryCode;
+use serde::{Deserialize, Serialize};
+
+use crate::{endpoints::json::JsonResponseBodySuccessJson, types::geo_point::GeoPoint};
+
+pub const COUNTRY_BONUS_METERS_DEFAULT: f64 = 1_000_000.0;
+pub const CONTINENT_BONUS_METERS_DEFAULT: f64 = 500_000.0;
+
+//
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Region {
+    pub id: Box<str>,
+    pub location: GeoPoint,
+    #[serde(default)]
+    pub countries: Vec<CountryCode>,
+    #[serde(default)]
+    pub continents: Vec<ContinentCode>,
+}
+
+impl Region {
+    pub fn new(id: impl AsRef<str>, location: GeoPoint) -> Self {
+        Self {
+            id: id.as_ref().into(),
+            location,
+            countries: vec![],
+            continents: vec![],
+        }
+    }
+
+    pub fn countries(mut self, countries: impl IntoIterator<Item = CountryCode>) -> Self {
+        self.countries = countries.into_iter().collect();
+        self
+    }
+
+    pub fn continents(mut self, continents: impl IntoIterator<Item = ContinentCode>) -> Self {
+        self.continents = continents.into_iter().collect();
+        self
+    }
+
+    /// `None` for codes not in `fields`, they never match.
+    pub fn affinity(
+        &self,
+        country_code: Option<&CountryCode>,
+        continent_code: Option<&ContinentCode>,
+    ) -> Affinity {
+        if country_code.is_some_and(|x| self.countries.contains(x)) {
+            Affinity::Country
+        } else if continent_code.is_some_and(|x| self.continents.contains(x)) {
+            Affinity::Continent
+        } else {
+            Affinity::None
+        }
+    }
+}
+
+//
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Affinity {
+    None,
+    Continent,
+    Country,
+}
+
+//
+#[derive(Debug, Clone)]
+pub struct Router {
+    pub regions: Vec<Region>,
+    pub country_bonus_meters: f64,
+    pub continent_bonus_meters: f64,
+}
+
+impl Router {
+    pub fn new(regions: impl IntoIterator<Item = Region>) -> Self {
+        Self {
+            regions: regions.into_iter().collect(),
+            country_bonus_meters: COUNTRY_BONUS_METERS_DEFAULT,
+            continent_bonus_meters: CONTINENT_BONUS_METERS_DEFAULT,
+        }
+    }
+
+    pub fn country_bonus_meters(mut self, meters: f64) -> Self {
+        self.country_bonus_meters = meters;
+        self
+    }
+
+    pub fn continent_bonus_meters(mut self, meters: f64) -> Self {
+        self.continent_bonus_meters = meters;
+        self
+    }
+
+    /// All regions, best first.
+    pub fn rank(&self, json: &JsonResponseBodySuccessJson) -> Vec<RankedRegion<'_>> {
+        let location = json.geo_point();
+        let country_code = json
+            .present
+            .contains("countryCode")
+            .then_some(&json.country_code);
+        let continent_code = json
+            .present
+            .contains("continentCode")
+            .then_some(&json.continent_code);
+
+        let mut ranked = self
+            .regions
+            .iter()
+            .map(|region| {
+                let affinity = region.affinity(country_code, continent_code);
+                let distance_meters = location.map(|x| x.distance_meters(&region.location));
+                let bonus = match affinity {
+                    Affinity::Country => self.country_bonus_meters,
+                    Affinity::Continent => self.continent_bonus_meters,
+                    Affinity::None => 0.0,
+                };
+                RankedRegion {
+                    region,
+                    affinity,
+                    distance_meters,
+                    score: distance_meters.map(|x| x - bonus),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // Stable, so equal regions keep the configured order.
+        ranked.sort_by(|a, b| match (a.score, b.score) {
+            (Some(a), Some(b)) => a.total_cmp(&b),
+            _ => b.affinity.cmp(&a.affinity),
+        });
+
+        ranked
+    }
+
+    /// `None` only when there are no regions.
+    pub fn nearest(&self, json: &JsonResponseBodySuccessJson) -> Option<&Region> {
+        self.rank(json).first().map(|x| x.region)
+    }
+}
+
+//
+#[derive(Debug, Clone, PartialEq)]
+pub struct RankedRegion<'a> {
+    pub region: &'a Region,
+    pub affinity: Affinity,
+    /// `None` when the client has no `lat`/`lon`.
+    pub distance_meters: Option<f64>,
+    /// Distance minus the affinity bonus, lower is better.
+    pub score: Option<f64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn router() -> Router {
+        Router::new([
+            Region::new("us-east", GeoPoint::new(39.03, -77.5)).continents([ContinentCode::NA]),
+            Region::new("eu-west", GeoPoint::new(53.35, -6.26)).continents([ContinentCode::EU]),
+            Region::new("ca-central", GeoPoint::new(43.65, -79.38)).countries([CountryCode::CA]),
+            Region::new("ap-northeast", GeoPoint::new(35.68, 139.69))
+                .continents([ContinentCode::AS]),
+        ])
+    }
+
+    fn success_json() -> JsonResponseBodySuccessJson {
+        serde_json::from_str(include_str!(
+            "../tests/response_body_json_files/json_default.json"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_rank() {
+        let json = success_json();
+
+        let router = router();
+        let ranked = router.rank(&json);
+        assert_eq!(
+            ranked.iter().map(|x| &*x.region.id).collect::<Vec<_>>(),
+            ["ca-central", "us-east", "eu-west", "ap-northeast"]
+        );
+        assert_eq!(ranked[0].affinity, Affinity::Country);
+        // `continentCode` is not in the default fields, its `AS` default must not count.
+        assert!(ranked[1..].iter().all(|x| x.affinity == Affinity::None));
+        assert!(ranked.iter().all(|x| x.distance_meters.is_some()));
+
+        // Distance wins without the bonuses, Ashburn is closer to Montreal than Ireland.
+        let router = self::router()
+            .country_bonus_meters(0.0)
+            .continent_bonus_meters(0.0);
+        assert_eq!(&*router.nearest(&json).unwrap().id, "ca-central");
+        assert_eq!(&*router.rank(&json)[1].region.id, "us-east");
+    }
+
+    #[test]
+    fn test_rank_without_location() {
+        let mut json = success_json();
+        json.present.remove("lat");
+        json.present.remove("lon");
+
+        let router = router();
+        let ranked = router.rank(&json);
+        assert!(ranked.iter().all(|x| x.distance_meters.is_none()));
+        assert_eq!(
+            ranked.iter().map(|x| &*x.region.id).collect::<Vec<_>>(),
+            ["ca-central", "us-east", "eu-west", "ap-northeast"]
+        );
+
+        json.present.remove("countryCode");
+        assert_eq!(&*router.nearest(&json).unwrap().id, "us-east");
+
+        json.continent_code = ContinentCode::AS;
+        json.present.insert("continentCode");
+        assert_eq!(&*router.nearest(&json).unwrap().id, "ap-northeast");
+
+        assert!(Router::new([]).nearest(&json).is_none());
+    }
+
+    #[test]
+    fn test_region_de() {
+        let region: Region = serde_json::from_str(
+            r#"{"id":"eu-west","location":{"lat":53.35,"lon":-6.26},"continents":["EU"]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            region,
+            Region::new("eu-west", GeoPoint::new(53.35, -6.26)).continents([ContinentCode::EU])
+        );
+    }
+}
@@ -0,0 +1,187 @@
+//! https://members.ip-api.com/
+
+use core::fmt;
+use std::{env, fs, io, path::Path};
+
+use url::Url;
+
+pub const ENV_KEY_IP_API_KEY: &str = "IP_API_KEY";
+pub const URL_QUERY_KEY: &str = "key";
+
+const REDACTED: &str = "***";
+
+//
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct ApiKey(Box<str>);
+
+impl ApiKey {
+    pub fn new(key: impl AsRef<str>) -> Self {
+        Self(key.as_ref().into())
+    }
+
+    /// Read the key from the `IP_API_KEY` environment variable, `None` when unset or blank.
+    pub fn from_env() -> Option<Self> {
+        Self::from_env_var(ENV_KEY_IP_API_KEY)
+    }
+
+    pub fn from_env_var(name: impl AsRef<str>) -> Option<Self> {
+        env::var(name.as_ref())
+            .ok()
+            .map(|x| x.trim().to_owned())
+            .filter(|x| !x.is_empty())
+            .map(Self::new)
+    }
+
+    /// Read the key from a file, surrounding whitespace (e.g. a trailing newline) is trimmed.
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let s = fs::read_to_string(path)?;
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "empty key file"));
+        }
+        Ok(Self::new(s))
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for ApiKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ApiKey").field(&REDACTED).finish()
+    }
+}
+
+impl fmt::Display for ApiKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", REDACTED)
+    }
+}
+
+impl From<&str> for ApiKey {
+    fn from(s: &str) -> Self {
+        Self::new(s)
+    }
+}
+
+impl From<String> for ApiKey {
+    fn from(s: String) -> Self {
+        Self(s.into())
+    }
+}
+
+impl From<Box<str>> for ApiKey {
+    fn from(s: Box<str>) -> Self {
+        Self(s)
+    }
+}
+
+//
+/// Replace the value of the `key` query parameter, for logging rendered request urls.
+pub fn redact_url(url: impl AsRef<str>) -> String {
+    let url = url.as_ref();
+    let mut parsed = match Url::parse(url) {
+        Ok(x) => x,
+        Err(_) => return url.to_owned(),
+    };
+    if !parsed.query_pairs().any(|(k, _)| k == URL_QUERY_KEY) {
+        return url.to_owned();
+    }
+
+    let pairs = parsed
+        .query_pairs()
+        .map(|(k, v)| {
+            if k == URL_QUERY_KEY {
+                (k.into_owned(), REDACTED.to_owned())
+            } else {
+                (k.into_owned(), v.into_owned())
+            }
+        })
+        .collect::<Vec<_>>();
+    parsed.query_pairs_mut().clear().extend_pairs(pairs);
+    parsed.to_string()
+}
+
+/// `redact_url` for every url in `text`, e.g. an error message, however the key is encoded.
+pub fn redact_urls(text: impl AsRef<str>) -> String {
+    let needle = format!("{}=", URL_QUERY_KEY);
+    let mut redacted = String::with_capacity(text.as_ref().len());
+    let mut rest = text.as_ref();
+    while let Some(i) = rest.find(&needle) {
+        let (head, tail) = rest.split_at(i + needle.len());
+        redacted.push_str(head);
+        rest = tail;
+        if matches!(head[..i].chars().last(), Some('?' | '&')) {
+            let end = tail
+                .find(|c: char| {
+                    c.is_whitespace() || matches!(c, '&' | '#' | ')' | '"' | '\'' | '>')
+                })
+                .unwrap_or(tail.len());
+            redacted.push_str(REDACTED);
+            rest = &tail[end..];
+        }
+    }
+    redacted.push_str(rest);
+    redacted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redaction() {
+        let key = ApiKey::new("foo");
+        assert_eq!(key.expose(), "foo");
+        assert_eq!(format!("{:?}", key), r#"ApiKey("***")"#);
+        assert_eq!(key.to_string(), "***");
+        assert_eq!(format!("{:?}", Some(key)), r#"Some(ApiKey("***"))"#);
+    }
+
+    #[test]
+    fn test_from_file() {
+        let path = env::temp_dir().join(format!("ip-api-api-key-{}", std::process::id()));
+
+        fs::write(&path, "foo\n").unwrap();
+        assert_eq!(ApiKey::from_file(&path).unwrap().expose(), "foo");
+
+        fs::write(&path, " \n").unwrap();
+        assert_eq!(
+            ApiKey::from_file(&path).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_redact_url() {
+        assert_eq!(
+            redact_url("https://pro.ip-api.com/json/24.48.0.1?key=foo&lang=en"),
+            "https://pro.ip-api.com/json/24.48.0.1?key=***&lang=en"
+        );
+        assert_eq!(
+            redact_url("http://ip-api.com/json/24.48.0.1?lang=en"),
+            "http://ip-api.com/json/24.48.0.1?lang=en"
+        );
+    }
+
+    #[test]
+    fn test_redact_urls() {
+        assert_eq!(
+            redact_urls(
+                "error sending request for url (https://pro.ip-api.com/json/24.48.0.1?key=f%2Bo%26o&lang=en)"
+            ),
+            "error sending request for url (https://pro.ip-api.com/json/24.48.0.1?key=***&lang=en)"
+        );
+        assert_eq!(
+            redact_urls("https://pro.ip-api.com/batch?key=foo&fields=query: timed out"),
+            "https://pro.ip-api.com/batch?key=***&fields=query: timed out"
+        );
+        assert_eq!(
+            redact_urls("monkey=1 http://ip-api.com/json/?lang=en"),
+            "monkey=1 http://ip-api.com/json/?lang=en"
+        );
+    }
+}
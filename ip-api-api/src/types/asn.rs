@@ -0,0 +1,149 @@
+//! https://ip-api.com/docs/api:json
+//!
+//! `as` is the AS number and organization, separated by space, e.g. "AS5769 Videotron Telecom Ltee".
+
+use core::{fmt, str::FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+
+//
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Asn(pub u32);
+
+impl FromStr for Asn {
+    type Err = AsnParseError;
+
+    /// Accepts "AS5769", "as5769" and "5769".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let n = s
+            .strip_prefix("AS")
+            .or_else(|| s.strip_prefix("as"))
+            .unwrap_or(s);
+        if n.is_empty() || !n.bytes().all(|x| x.is_ascii_digit()) {
+            return Err(AsnParseError::Invalid(s.into()));
+        }
+        n.parse()
+            .map(Self)
+            .map_err(|_| AsnParseError::Invalid(s.into()))
+    }
+}
+
+impl fmt::Display for Asn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "AS{}", self.0)
+    }
+}
+
+impl From<u32> for Asn {
+    fn from(n: u32) -> Self {
+        Self(n)
+    }
+}
+
+impl<'de> Deserialize<'de> for Asn {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = Box::<str>::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+impl Serialize for Asn {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+//
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AutonomousSystem {
+    pub number: Asn,
+    pub name: Box<str>,
+}
+
+impl FromStr for AutonomousSystem {
+    type Err = AsnParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (number, name) = s.split_once(' ').unwrap_or((s, ""));
+        Ok(Self {
+            number: number.parse()?,
+            name: name.trim().into(),
+        })
+    }
+}
+
+impl fmt::Display for AutonomousSystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.name.is_empty() {
+            write!(f, "{}", self.number)
+        } else {
+            write!(f, "{} {}", self.number, self.name)
+        }
+    }
+}
+
+//
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsnParseError {
+    Invalid(Box<str>),
+}
+
+impl fmt::Display for AsnParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for AsnParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_asn() {
+        assert_eq!("AS5769".parse::<Asn>().unwrap(), Asn(5769));
+        assert_eq!("as5769".parse::<Asn>().unwrap(), Asn(5769));
+        assert_eq!("5769".parse::<Asn>().unwrap(), Asn(5769));
+        assert_eq!(Asn(5769).to_string(), "AS5769");
+
+        for s in ["", "AS", "AS-1", "ASfoo", "AS4294967296", "AS 5769"] {
+            assert!(s.parse::<Asn>().is_err(), "{}", s);
+        }
+
+        assert_eq!(serde_json::to_string(&Asn(15169)).unwrap(), r#""AS15169""#);
+        assert_eq!(
+            serde_json::from_str::<Asn>(r#""AS15169""#).unwrap(),
+            Asn(15169)
+        );
+    }
+
+    #[test]
+    fn test_autonomous_system() {
+        let x = "AS5769 Videotron Telecom Ltee"
+            .parse::<AutonomousSystem>()
+            .unwrap();
+        assert_eq!(x.number, Asn(5769));
+        assert_eq!(x.name, "Videotron Telecom Ltee".into());
+        assert_eq!(x.to_string(), "AS5769 Videotron Telecom Ltee");
+
+        let x = "AS15169".parse::<AutonomousSystem>().unwrap();
+        assert_eq!(x.number, Asn(15169));
+        assert_eq!(x.name, "".into());
+        assert_eq!(x.to_string(), "AS15169");
+
+        assert!("".parse::<AutonomousSystem>().is_err());
+        assert!(
+            "Videotron Telecom Ltee"
+                .parse::<AutonomousSystem>()
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! https://www.itu.int/rec/T-REC-E.164
+//!
+//! `callingCode` is the country calling code without the leading `+`, e.g. "1" or "44".
+
+use core::{fmt, str::FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+
+/// E.164 country codes have at most 3 digits.
+pub const MAX_DIGITS: usize = 3;
+
+//
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CallingCode {
+    Code(u16),
+    /// Anything else, kept as is.
+    Other(Box<str>),
+}
+
+impl CallingCode {
+    pub fn code(&self) -> Option<u16> {
+        match self {
+            Self::Code(n) => Some(*n),
+            Self::Other(_) => None,
+        }
+    }
+
+    /// e.g. "+1"
+    pub fn to_e164_prefix(&self) -> Option<String> {
+        self.code().map(|n| format!("+{}", n))
+    }
+}
+
+impl Default for CallingCode {
+    fn default() -> Self {
+        Self::Other(Default::default())
+    }
+}
+
+impl FromStr for CallingCode {
+    type Err = CallingCodeParseError;
+
+    /// Accepts "1" and "+1".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s.strip_prefix('+').unwrap_or(s);
+        if digits.is_empty()
+            || digits.len() > MAX_DIGITS
+            || digits.starts_with('0')
+            || !digits.bytes().all(|x| x.is_ascii_digit())
+        {
+            return Err(CallingCodeParseError::Invalid(s.into()));
+        }
+        digits
+            .parse()
+            .map(Self::Code)
+            .map_err(|_| CallingCodeParseError::Invalid(s.into()))
+    }
+}
+
+impl fmt::Display for CallingCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Code(n) => write!(f, "{}", n),
+            Self::Other(s) => f.write_str(s),
+        }
+    }
+}
+
+/// Lenient, any invalid value becomes `Other`, so an odd `callingCode` does not fail the response.
+impl<'de> Deserialize<'de> for CallingCode {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct CallingCodeVisitor;
+
+        impl de::Visitor<'_> for CallingCodeVisitor {
+            type Value = CallingCode;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("country calling code")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(v.parse().unwrap_or_else(|_| CallingCode::Other(v.into())))
+            }
+        }
+
+        deserializer.deserialize_str(CallingCodeVisitor)
+    }
+}
+
+impl Serialize for CallingCode {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+//
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CallingCodeParseError {
+    Invalid(Box<str>),
+}
+
+impl fmt::Display for CallingCodeParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for CallingCodeParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calling_code() {
+        assert_eq!("1".parse::<CallingCode>().unwrap(), CallingCode::Code(1));
+        assert_eq!("+44".parse::<CallingCode>().unwrap(), CallingCode::Code(44));
+        assert_eq!(CallingCode::Code(1).to_string(), "1");
+        assert_eq!(CallingCode::Code(1).to_e164_prefix(), Some("+1".to_owned()));
+
+        for s in ["", "+", "0", "01", "1234", "1-684", "a"] {
+            assert!(s.parse::<CallingCode>().is_err(), "{}", s);
+        }
+
+        assert_eq!(
+            serde_json::from_str::<CallingCode>(r#""86""#).unwrap(),
+            CallingCode::Code(86)
+        );
+        assert_eq!(
+            serde_json::from_str::<CallingCode>(r#""1-684""#).unwrap(),
+            CallingCode::Other("1-684".into())
+        );
+        assert_eq!(
+            serde_json::to_string(&CallingCode::Code(86)).unwrap(),
+            r#""86""#
+        );
+        assert_eq!(
+            serde_json::to_string(&CallingCode::Other("1-684".into())).unwrap(),
+            r#""1-684""#
+        );
+    }
+}
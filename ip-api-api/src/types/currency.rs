@@ -0,0 +1,527 @@
+//! https://www.iso.org/iso-4217-currency-codes.html
+
+use core::{fmt, str::FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+
+macro_rules! currency_code {
+    (
+        $( #[$meta:meta] )*
+        $pub:vis enum $name:ident {
+            $(
+                $( #[$variant_meta:meta] )*
+                $variant:ident,
+            )+
+        }
+    ) => {
+        $(#[$meta])*
+        $pub enum $name {
+            $(
+                $( #[$variant_meta] )*
+                $variant,
+            )+
+            Other(Box<str>),
+        }
+
+        impl $name {
+            pub const VARS: &'static [$name] = &[
+                $(
+                    $name::$variant,
+                )+
+            ];
+
+            fn from_known(s: &str) -> Option<Self> {
+                match s {
+                    $(
+                        ::core::stringify!($variant) => Some(Self::$variant),
+                    )+
+                    _ => None,
+                }
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match self {
+                    $(
+                        Self::$variant => f.write_str(::core::stringify!($variant)),
+                    )+
+                    Self::Other(s) => f.write_str(s),
+                }
+            }
+        }
+    };
+}
+
+currency_code! {
+    /// ISO 4217 alphabetic code, `Other` for codes not in the bundled list.
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    pub enum Currency {
+        /// UAE Dirham
+        AED,
+        /// Afghani
+        AFN,
+        /// Lek
+        ALL,
+        /// Armenian Dram
+        AMD,
+        /// Netherlands Antillean Guilder
+        ANG,
+        /// Kwanza
+        AOA,
+        /// Argentine Peso
+        ARS,
+        /// Australian Dollar
+        AUD,
+        /// Aruban Florin
+        AWG,
+        /// Azerbaijan Manat
+        AZN,
+        /// Convertible Mark
+        BAM,
+        /// Barbados Dollar
+        BBD,
+        /// Taka
+        BDT,
+        /// Bulgarian Lev
+        BGN,
+        /// Bahraini Dinar
+        BHD,
+        /// Burundi Franc
+        BIF,
+        /// Bermudian Dollar
+        BMD,
+        /// Brunei Dollar
+        BND,
+        /// Boliviano
+        BOB,
+        /// Mvdol
+        BOV,
+        /// Brazilian Real
+        BRL,
+        /// Bahamian Dollar
+        BSD,
+        /// Ngultrum
+        BTN,
+        /// Pula
+        BWP,
+        /// Belarusian Ruble
+        BYN,
+        /// Belize Dollar
+        BZD,
+        /// Canadian Dollar
+        CAD,
+        /// Congolese Franc
+        CDF,
+        /// WIR Euro
+        CHE,
+        /// Swiss Franc
+        CHF,
+        /// WIR Franc
+        CHW,
+        /// Unidad de Fomento
+        CLF,
+        /// Chilean Peso
+        CLP,
+        /// Yuan Renminbi
+        CNY,
+        /// Colombian Peso
+        COP,
+        /// Unidad de Valor Real
+        COU,
+        /// Costa Rican Colon
+        CRC,
+        /// Peso Convertible
+        CUC,
+        /// Cuban Peso
+        CUP,
+        /// Cabo Verde Escudo
+        CVE,
+        /// Czech Koruna
+        CZK,
+        /// Djibouti Franc
+        DJF,
+        /// Danish Krone
+        DKK,
+        /// Dominican Peso
+        DOP,
+        /// Algerian Dinar
+        DZD,
+        /// Egyptian Pound
+        EGP,
+        /// Nakfa
+        ERN,
+        /// Ethiopian Birr
+        ETB,
+        /// Euro
+        EUR,
+        /// Fiji Dollar
+        FJD,
+        /// Falkland Islands Pound
+        FKP,
+        /// Pound Sterling
+        GBP,
+        /// Lari
+        GEL,
+        /// Ghana Cedi
+        GHS,
+        /// Gibraltar Pound
+        GIP,
+        /// Dalasi
+        GMD,
+        /// Guinean Franc
+        GNF,
+        /// Quetzal
+        GTQ,
+        /// Guyana Dollar
+        GYD,
+        /// Hong Kong Dollar
+        HKD,
+        /// Lempira
+        HNL,
+        /// Kuna
+        HRK,
+        /// Gourde
+        HTG,
+        /// Forint
+        HUF,
+        /// Rupiah
+        IDR,
+        /// New Israeli Sheqel
+        ILS,
+        /// Indian Rupee
+        INR,
+        /// Iraqi Dinar
+        IQD,
+        /// Iranian Rial
+        IRR,
+        /// Iceland Krona
+        ISK,
+        /// Jamaican Dollar
+        JMD,
+        /// Jordanian Dinar
+        JOD,
+        /// Yen
+        JPY,
+        /// Kenyan Shilling
+        KES,
+        /// Som
+        KGS,
+        /// Riel
+        KHR,
+        /// Comorian Franc
+        KMF,
+        /// North Korean Won
+        KPW,
+        /// Won
+        KRW,
+        /// Kuwaiti Dinar
+        KWD,
+        /// Cayman Islands Dollar
+        KYD,
+        /// Tenge
+        KZT,
+        /// Lao Kip
+        LAK,
+        /// Lebanese Pound
+        LBP,
+        /// Sri Lanka Rupee
+        LKR,
+        /// Liberian Dollar
+        LRD,
+        /// Loti
+        LSL,
+        /// Libyan Dinar
+        LYD,
+        /// Moroccan Dirham
+        MAD,
+        /// Moldovan Leu
+        MDL,
+        /// Malagasy Ariary
+        MGA,
+        /// Denar
+        MKD,
+        /// Kyat
+        MMK,
+        /// Tugrik
+        MNT,
+        /// Pataca
+        MOP,
+        /// Ouguiya
+        MRU,
+        /// Mauritius Rupee
+        MUR,
+        /// Rufiyaa
+        MVR,
+        /// Malawi Kwacha
+        MWK,
+        /// Mexican Peso
+        MXN,
+        /// Mexican Unidad de Inversion (UDI)
+        MXV,
+        /// Malaysian Ringgit
+        MYR,
+        /// Mozambique Metical
+        MZN,
+        /// Namibia Dollar
+        NAD,
+        /// Naira
+        NGN,
+        /// Cordoba Oro
+        NIO,
+        /// Norwegian Krone
+        NOK,
+        /// Nepalese Rupee
+        NPR,
+        /// New Zealand Dollar
+        NZD,
+        /// Rial Omani
+        OMR,
+        /// Balboa
+        PAB,
+        /// Sol
+        PEN,
+        /// Kina
+        PGK,
+        /// Philippine Peso
+        PHP,
+        /// Pakistan Rupee
+        PKR,
+        /// Zloty
+        PLN,
+        /// Guarani
+        PYG,
+        /// Qatari Rial
+        QAR,
+        /// Romanian Leu
+        RON,
+        /// Serbian Dinar
+        RSD,
+        /// Russian Ruble
+        RUB,
+        /// Rwanda Franc
+        RWF,
+        /// Saudi Riyal
+        SAR,
+        /// Solomon Islands Dollar
+        SBD,
+        /// Seychelles Rupee
+        SCR,
+        /// Sudanese Pound
+        SDG,
+        /// Swedish Krona
+        SEK,
+        /// Singapore Dollar
+        SGD,
+        /// Saint Helena Pound
+        SHP,
+        /// Leone
+        SLE,
+        /// Leone
+        SLL,
+        /// Somali Shilling
+        SOS,
+        /// Surinam Dollar
+        SRD,
+        /// South Sudanese Pound
+        SSP,
+        /// Dobra
+        STN,
+        /// El Salvador Colon
+        SVC,
+        /// Syrian Pound
+        SYP,
+        /// Lilangeni
+        SZL,
+        /// Baht
+        THB,
+        /// Somoni
+        TJS,
+        /// Turkmenistan New Manat
+        TMT,
+        /// Tunisian Dinar
+        TND,
+        /// Pa’anga
+        TOP,
+        /// Turkish Lira
+        TRY,
+        /// Trinidad and Tobago Dollar
+        TTD,
+        /// New Taiwan Dollar
+        TWD,
+        /// Tanzanian Shilling
+        TZS,
+        /// Hryvnia
+        UAH,
+        /// Uganda Shilling
+        UGX,
+        /// US Dollar
+        USD,
+        /// US Dollar (Next day)
+        USN,
+        /// Uruguay Peso en Unidades Indexadas (UI)
+        UYI,
+        /// Peso Uruguayo
+        UYU,
+        /// Unidad Previsional
+        UYW,
+        /// Uzbekistan Sum
+        UZS,
+        /// Bolívar Soberano
+        VED,
+        /// Bolívar Soberano
+        VES,
+        /// Dong
+        VND,
+        /// Vatu
+        VUV,
+        /// Tala
+        WST,
+        /// CFA Franc BEAC
+        XAF,
+        /// Silver
+        XAG,
+        /// Gold
+        XAU,
+        /// Bond Markets Unit European Composite Unit (EURCO)
+        XBA,
+        /// Bond Markets Unit European Monetary Unit (E.M.U.-6)
+        XBB,
+        /// Bond Markets Unit European Unit of Account 9 (E.U.A.-9)
+        XBC,
+        /// Bond Markets Unit European Unit of Account 17 (E.U.A.-17)
+        XBD,
+        /// East Caribbean Dollar
+        XCD,
+        /// SDR (Special Drawing Right)
+        XDR,
+        /// CFA Franc BCEAO
+        XOF,
+        /// Palladium
+        XPD,
+        /// CFP Franc
+        XPF,
+        /// Platinum
+        XPT,
+        /// Sucre
+        XSU,
+        /// Codes specifically reserved for testing purposes
+        XTS,
+        /// ADB Unit of Account
+        XUA,
+        /// The codes assigned for transactions where no currency is involved
+        XXX,
+        /// Yemeni Rial
+        YER,
+        /// Rand
+        ZAR,
+        /// Zambian Kwacha
+        ZMW,
+        /// Zimbabwe Dollar
+        ZWL,
+    }
+}
+
+impl Default for Currency {
+    fn default() -> Self {
+        Self::Other(Default::default())
+    }
+}
+
+impl FromStr for Currency {
+    type Err = CurrencyParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(x) = Self::from_known(s) {
+            return Ok(x);
+        }
+        if s.len() == 3 && s.bytes().all(|x| x.is_ascii_uppercase()) {
+            Ok(Self::Other(s.into()))
+        } else {
+            Err(CurrencyParseError::Invalid(s.into()))
+        }
+    }
+}
+
+/// Lenient, any unknown value becomes `Other`, so an odd `currency` does not fail the response.
+impl<'de> Deserialize<'de> for Currency {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct CurrencyVisitor;
+
+        impl de::Visitor<'_> for CurrencyVisitor {
+            type Value = Currency;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("ISO 4217 currency code")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(Currency::from_known(v).unwrap_or_else(|| Currency::Other(v.into())))
+            }
+        }
+
+        deserializer.deserialize_str(CurrencyVisitor)
+    }
+}
+
+impl Serialize for Currency {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+//
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CurrencyParseError {
+    Invalid(Box<str>),
+}
+
+impl fmt::Display for CurrencyParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for CurrencyParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_currency() {
+        assert_eq!(Currency::VARS.len(), 181);
+
+        assert_eq!("CAD".parse::<Currency>().unwrap(), Currency::CAD);
+        assert_eq!(Currency::CAD.to_string(), "CAD");
+        assert_eq!(
+            "ZZZ".parse::<Currency>().unwrap(),
+            Currency::Other("ZZZ".into())
+        );
+        assert!("cad".parse::<Currency>().is_err());
+        assert!("".parse::<Currency>().is_err());
+
+        assert_eq!(
+            serde_json::from_str::<Currency>(r#""EUR""#).unwrap(),
+            Currency::EUR
+        );
+        assert_eq!(
+            serde_json::from_str::<Currency>(r#""""#).unwrap(),
+            Currency::default()
+        );
+        assert_eq!(serde_json::to_string(&Currency::EUR).unwrap(), r#""EUR""#);
+
+        for x in Currency::VARS {
+            assert_eq!(&x.to_string().parse::<Currency>().unwrap(), x);
+        }
+    }
+}
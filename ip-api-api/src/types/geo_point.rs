@@ -0,0 +1,165 @@
+//! WGS 84 coordinates from `lat` and `lon`.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+/// Mean earth radius.
+pub const EARTH_RADIUS_METERS: f64 = 6_371_008.8;
+
+//
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq)]
+pub struct GeoPoint {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl GeoPoint {
+    pub fn new(lat: f64, lon: f64) -> Self {
+        Self { lat, lon }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.lat.is_finite()
+            && self.lon.is_finite()
+            && (-90.0..=90.0).contains(&self.lat)
+            && (-180.0..=180.0).contains(&self.lon)
+    }
+
+    /// Great-circle distance, by the haversine formula.
+    pub fn distance_meters(&self, other: &Self) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let d_lat = lat2 - lat1;
+        let d_lon = (other.lon - self.lon).to_radians();
+
+        let h = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_METERS * h.sqrt().min(1.0).asin()
+    }
+
+    pub fn distance_km(&self, other: &Self) -> f64 {
+        self.distance_meters(other) / 1000.0
+    }
+
+    /// Initial bearing towards `other`, in degrees clockwise from north, `0..360`.
+    pub fn bearing_degrees(&self, other: &Self) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let d_lon = (other.lon - self.lon).to_radians();
+
+        let y = d_lon.sin() * lat2.cos();
+        let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * d_lon.cos();
+        (y.atan2(x).to_degrees() + 360.0) % 360.0
+    }
+
+    /// GeoJSON `Point`, note the `[lon, lat]` order.
+    pub fn to_geojson(&self) -> Value {
+        json!({
+            "type": "Point",
+            "coordinates": [self.lon, self.lat],
+        })
+    }
+}
+
+//
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq)]
+pub struct BoundingBox {
+    /// South-west corner.
+    pub min: GeoPoint,
+    /// North-east corner, `max.lon < min.lon` when crossing the antimeridian.
+    pub max: GeoPoint,
+}
+
+impl BoundingBox {
+    pub fn new(min: GeoPoint, max: GeoPoint) -> Self {
+        Self { min, max }
+    }
+
+    /// Approximate box enclosing the circle of `radius_meters` around `center`.
+    pub fn around(center: &GeoPoint, radius_meters: f64) -> Self {
+        let d_lat = (radius_meters / EARTH_RADIUS_METERS).to_degrees();
+        let min_lat = (center.lat - d_lat).max(-90.0);
+        let max_lat = (center.lat + d_lat).min(90.0);
+
+        if min_lat <= -90.0 || max_lat >= 90.0 {
+            return Self::new(
+                GeoPoint::new(min_lat, -180.0),
+                GeoPoint::new(max_lat, 180.0),
+            );
+        }
+
+        let d_lon =
+            (radius_meters / (EARTH_RADIUS_METERS * center.lat.to_radians().cos())).to_degrees();
+        if d_lon >= 180.0 {
+            return Self::new(
+                GeoPoint::new(min_lat, -180.0),
+                GeoPoint::new(max_lat, 180.0),
+            );
+        }
+
+        let wrap = |lon: f64| (lon + 540.0) % 360.0 - 180.0;
+        Self::new(
+            GeoPoint::new(min_lat, wrap(center.lon - d_lon)),
+            GeoPoint::new(max_lat, wrap(center.lon + d_lon)),
+        )
+    }
+
+    pub fn contains(&self, point: &GeoPoint) -> bool {
+        if point.lat < self.min.lat || point.lat > self.max.lat {
+            return false;
+        }
+        if self.min.lon <= self.max.lon {
+            self.min.lon <= point.lon && point.lon <= self.max.lon
+        } else {
+            point.lon >= self.min.lon || point.lon <= self.max.lon
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MONTREAL: GeoPoint = GeoPoint {
+        lat: 45.4995,
+        lon: -73.5848,
+    };
+    const ASHBURN: GeoPoint = GeoPoint {
+        lat: 39.03,
+        lon: -77.5,
+    };
+
+    #[test]
+    fn test_distance_and_bearing() {
+        assert!((MONTREAL.distance_meters(&ASHBURN) - 787_972.1).abs() < 1.0);
+        assert!((MONTREAL.distance_km(&ASHBURN) - 787.97).abs() < 0.01);
+        assert_eq!(MONTREAL.distance_meters(&MONTREAL), 0.0);
+        assert!((MONTREAL.bearing_degrees(&ASHBURN) - 205.464).abs() < 0.001);
+
+        assert!(MONTREAL.is_valid());
+        assert!(!GeoPoint::new(91.0, 0.0).is_valid());
+        assert!(!GeoPoint::new(0.0, f64::NAN).is_valid());
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let bbox = BoundingBox::around(&MONTREAL, 1_000_000.0);
+        assert!(bbox.contains(&MONTREAL));
+        assert!(bbox.contains(&ASHBURN));
+        assert!(!bbox.contains(&GeoPoint::new(48.8566, 2.3522)));
+
+        let bbox = BoundingBox::new(GeoPoint::new(-20.0, 170.0), GeoPoint::new(20.0, -170.0));
+        assert!(bbox.contains(&GeoPoint::new(0.0, 179.0)));
+        assert!(bbox.contains(&GeoPoint::new(0.0, -175.0)));
+        assert!(!bbox.contains(&GeoPoint::new(0.0, 0.0)));
+
+        let bbox = BoundingBox::around(&GeoPoint::new(0.0, 179.5), 200_000.0);
+        assert!(bbox.min.lon > bbox.max.lon);
+        assert!(bbox.contains(&GeoPoint::new(0.0, -179.5)));
+    }
+
+    #[test]
+    fn test_to_geojson() {
+        assert_eq!(
+            MONTREAL.to_geojson(),
+            json!({"type": "Point", "coordinates": [-73.5848, 45.4995]})
+        );
+    }
+}
@@ -1 +1,7 @@
+pub mod api_key;
+pub mod asn;
+pub mod calling_code;
+pub mod currency;
+pub mod geo_point;
 pub mod lang;
+pub mod subdivision;
@@ -0,0 +1,88 @@
+//! https://www.iso.org/iso-3166-country-codes.html
+//!
+//! ISO 3166-2 subdivision codes, e.g. "CA-QC" from `countryCode` "CA" and `region` "QC".
+
+mod codes;
+
+use country_code::{CountryCode, iso3166_2::SubdivisionCode};
+
+/// Whether `code` (e.g. "CA-QC") is in the bundled ISO 3166-2 table.
+pub fn is_known(code: &str) -> bool {
+    codes::CODES.binary_search(&code).is_ok()
+}
+
+/// Combine a country code and an ip-api `region`, `None` unless the result is in the bundled table.
+pub fn subdivision_code(country_code: &CountryCode, region: &str) -> Option<SubdivisionCode> {
+    if region.is_empty() {
+        return None;
+    }
+    let code = format!("{}-{}", country_code, region);
+    if !is_known(&code) {
+        return None;
+    }
+    code.parse().ok()
+}
+
+/// All bundled codes of a country, e.g. "CA-AB" .. "CA-YT" for `CountryCode::CA`.
+pub fn subdivision_codes_of(country_code: &CountryCode) -> impl Iterator<Item = &'static str> {
+    let prefix = format!("{}-", country_code);
+    let start = codes::CODES.partition_point(|x| *x < prefix.as_str());
+    codes::CODES[start..]
+        .iter()
+        .take_while(move |x| x.starts_with(&prefix))
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_sorted() {
+        assert!(codes::CODES.windows(2).all(|x| x[0] < x[1]));
+    }
+
+    /// Against iso-codes 4.15 `iso_3166-2.json`, see `scripts/gen_subdivision_codes.py`.
+    #[test]
+    fn test_codes_source() {
+        assert_eq!(codes::CODES.len(), 5127);
+        assert_eq!(codes::CODES.first(), Some(&"AD-02"));
+        assert_eq!(codes::CODES.last(), Some(&"ZW-MW"));
+        for code in ["GB-ENG", "GB-LND", "JP-13", "US-DC", "US-PR", "BR-SP"] {
+            assert!(is_known(code), "{}", code);
+        }
+        // Not in 4.15.
+        for code in ["CA-NF", "CN-71", "FR-75C"] {
+            assert!(!is_known(code), "{}", code);
+        }
+    }
+
+    #[test]
+    fn test_subdivision_code() {
+        assert!(is_known("CA-QC"));
+        assert!(!is_known("CA-ZZ"));
+
+        assert_eq!(
+            subdivision_code(&CountryCode::CA, "QC")
+                .unwrap()
+                .to_string(),
+            "CA-QC"
+        );
+        assert_eq!(
+            subdivision_code(&CountryCode::US, "VA").unwrap(),
+            SubdivisionCode::US(country_code::iso3166_2::USSubdivisionCode::VA)
+        );
+        assert_eq!(
+            subdivision_code(&CountryCode::CN, "BJ")
+                .unwrap()
+                .to_string(),
+            "CN-BJ"
+        );
+        assert!(subdivision_code(&CountryCode::CA, "").is_none());
+        assert!(subdivision_code(&CountryCode::CA, "ZZ").is_none());
+        assert!(subdivision_code(&CountryCode::default(), "QC").is_none());
+
+        assert_eq!(subdivision_codes_of(&CountryCode::CA).count(), 13);
+        assert_eq!(subdivision_codes_of(&CountryCode::CA).next(), Some("CA-AB"));
+    }
+}
@@ -0,0 +1,152 @@
+//! Annotate access log lines with the country code and ASN of their client.
+//!
+//! Table keeps the log format, appending `"CA" "AS5769"`, the other formats write one record
+//! per line.
+
+use std::io::{self, Write};
+
+use ip_api_api::{
+    access_log, endpoints::json::JsonResponseBodySuccessJson, lookup_cache::LookupCache,
+};
+use serde_json::Value;
+
+use crate::output::{self, Format, Record};
+
+/// Regardless of `--fields`, the cache is shared between runs.
+pub const FIELDS: &str = "status,message,country,countryCode,as,query";
+pub const COLUMNS: &[&str] = &["ip", "countryCode", "country", "as", "line"];
+
+pub fn write(
+    format: Format,
+    lines: &[String],
+    cache: &LookupCache,
+    mut w: impl Write,
+) -> io::Result<()> {
+    match format {
+        Format::Table => {
+            for line in lines {
+                let result = access_log::remote_addr(line).and_then(|ip| cache.get(&ip));
+                writeln!(w, "{}", access_log::annotate(line, result))?;
+            }
+            Ok(())
+        }
+        format => {
+            let header = COLUMNS.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+            let records = lines
+                .iter()
+                .map(|line| record(line, cache))
+                .collect::<Vec<_>>();
+            output::write(format, &header, &records, w)
+        }
+    }
+}
+
+fn record(line: &str, cache: &LookupCache) -> Record {
+    let ip = access_log::remote_addr(line);
+    let success = ip
+        .and_then(|ip| cache.get(&ip))
+        .and_then(|x| x.as_success());
+    // Not in `fields` of the cached lookup, rather than the default.
+    let field = |name: &str, f: fn(&JsonResponseBodySuccessJson) -> String| {
+        success
+            .filter(|x| x.present.contains(name))
+            .map_or(Value::Null, |x| Value::String(f(x)))
+    };
+
+    Record(vec![
+        (
+            "ip".to_owned(),
+            ip.map_or(Value::Null, |x| Value::String(x.to_string())),
+        ),
+        (
+            "countryCode".to_owned(),
+            field("countryCode", |x| x.country_code.to_string()),
+        ),
+        (
+            "country".to_owned(),
+            field("country", |x| x.country.to_string()),
+        ),
+        ("as".to_owned(), field("as", |x| x.r#as.to_string())),
+        ("line".to_owned(), Value::String(line.to_owned())),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache() -> LookupCache {
+        let mut cache = LookupCache::new();
+        cache
+            .insert(
+                "24.48.0.1".parse().unwrap(),
+                serde_json::from_str(
+                    r#"{"status":"success","country":"Canada","countryCode":"CA","as":"AS5769 Videotron Telecom Ltee","query":"24.48.0.1"}"#,
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        cache
+            .insert(
+                "10.0.0.1".parse().unwrap(),
+                serde_json::from_str(
+                    r#"{"status":"fail","message":"private range","query":"10.0.0.1"}"#,
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        cache
+    }
+
+    fn render(format: Format) -> String {
+        let lines = [
+            r#"24.48.0.1 - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.0" 200 -"#,
+            r#"10.0.0.1 - - [10/Oct/2000:13:55:37 -0700] "GET / HTTP/1.0" 200 -"#,
+            "garbage",
+        ]
+        .map(ToOwned::to_owned);
+        let mut buf = vec![];
+        write(format, &lines, &cache(), &mut buf).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn test_write() {
+        assert_eq!(
+            render(Format::Table),
+            r#"24.48.0.1 - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.0" 200 - "CA" "AS5769"
+10.0.0.1 - - [10/Oct/2000:13:55:37 -0700] "GET / HTTP/1.0" 200 - "-" "-"
+garbage "-" "-"
+"#
+        );
+        assert_eq!(
+            render(Format::Csv),
+            r#"ip,countryCode,country,as,line
+24.48.0.1,CA,Canada,AS5769 Videotron Telecom Ltee,"24.48.0.1 - - [10/Oct/2000:13:55:36 -0700] ""GET / HTTP/1.0"" 200 -"
+10.0.0.1,,,,"10.0.0.1 - - [10/Oct/2000:13:55:37 -0700] ""GET / HTTP/1.0"" 200 -"
+,,,,garbage
+"#
+        );
+        assert!(render(Format::Ndjson).starts_with(r#"{"ip":"24.48.0.1","countryCode":"CA","#));
+    }
+
+    #[test]
+    fn test_record_without_fields() {
+        let mut cache = LookupCache::new();
+        cache
+            .insert(
+                "24.48.0.1".parse().unwrap(),
+                serde_json::from_str(
+                    r#"{"status":"success","country":"Canada","query":"24.48.0.1"}"#,
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        let line = r#"24.48.0.1 - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.0" 200 -"#;
+
+        let record = record(line, &cache);
+        assert_eq!(record.get("countryCode"), Some(&Value::Null));
+        assert_eq!(record.get("country"), Some(&Value::from("Canada")));
+        assert_eq!(record.get("as"), Some(&Value::Null));
+    }
+}
@@ -0,0 +1,123 @@
+//! Queries or log lines from arguments, files or stdin.
+//!
+//! One or more queries per line, separated by `,` or whitespace, `#` starts a comment.
+
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::{Path, PathBuf},
+};
+
+pub const STDIN_PATH: &str = "-";
+
+/// Stdin is read when there are neither `args` nor `inputs`, or an input is `-`.
+pub fn read_queries(
+    args: &[String],
+    inputs: &[PathBuf],
+    mut stdin: impl BufRead,
+) -> io::Result<Vec<String>> {
+    let mut queries = vec![];
+    for arg in args {
+        queries.extend(split_line(arg));
+    }
+
+    if args.is_empty() && inputs.is_empty() {
+        read_lines(&mut stdin, &mut queries)?;
+    }
+    for input in inputs {
+        if input == Path::new(STDIN_PATH) {
+            read_lines(&mut stdin, &mut queries)?;
+        } else {
+            read_lines(&mut BufReader::new(File::open(input)?), &mut queries)?;
+        }
+    }
+
+    Ok(queries)
+}
+
+fn read_lines(reader: &mut impl BufRead, queries: &mut Vec<String>) -> io::Result<()> {
+    for line in reader.lines() {
+        queries.extend(split_line(&line?));
+    }
+    Ok(())
+}
+
+/// Lines as they are, stdin is read when there are no `inputs` or an input is `-`.
+pub fn read_raw_lines(inputs: &[PathBuf], mut stdin: impl BufRead) -> io::Result<Vec<String>> {
+    if inputs.is_empty() {
+        return stdin.lines().collect();
+    }
+
+    let mut lines = vec![];
+    for input in inputs {
+        if input == Path::new(STDIN_PATH) {
+            for line in (&mut stdin).lines() {
+                lines.push(line?);
+            }
+        } else {
+            for line in BufReader::new(File::open(input)?).lines() {
+                lines.push(line?);
+            }
+        }
+    }
+    Ok(lines)
+}
+
+fn split_line(line: &str) -> impl Iterator<Item = String> + '_ {
+    let line = line.split_once('#').map_or(line, |(x, _)| x);
+    line.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|x| !x.is_empty())
+        .map(ToOwned::to_owned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Write as _;
+
+    #[test]
+    fn test_read_queries() {
+        let stdin = "8.8.8.8\n\n# comment\n1.1.1.1, 9.9.9.9 # trailing\n".as_bytes();
+
+        assert_eq!(
+            read_queries(&["8.8.8.8,4.4.4.4".to_owned()], &[], stdin).unwrap(),
+            ["8.8.8.8", "4.4.4.4"]
+        );
+        assert_eq!(
+            read_queries(&[], &[], stdin).unwrap(),
+            ["8.8.8.8", "1.1.1.1", "9.9.9.9"]
+        );
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "24.48.0.1").unwrap();
+        assert_eq!(
+            read_queries(
+                &["4.4.4.4".to_owned()],
+                &[file.path().to_owned(), STDIN_PATH.into()],
+                stdin
+            )
+            .unwrap(),
+            ["4.4.4.4", "24.48.0.1", "8.8.8.8", "1.1.1.1", "9.9.9.9"]
+        );
+
+        assert!(read_queries(&[], &["/nonexistent".into()], stdin).is_err());
+    }
+
+    #[test]
+    fn test_read_raw_lines() {
+        let stdin = "8.8.8.8 - - # not a comment\n\n".as_bytes();
+
+        assert_eq!(
+            read_raw_lines(&[], stdin).unwrap(),
+            ["8.8.8.8 - - # not a comment", ""]
+        );
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "24.48.0.1 -").unwrap();
+        assert_eq!(
+            read_raw_lines(&[STDIN_PATH.into(), file.path().to_owned()], stdin).unwrap(),
+            ["8.8.8.8 - - # not a comment", "", "24.48.0.1 -"]
+        );
+    }
+}
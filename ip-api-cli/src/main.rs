@@ -0,0 +1,389 @@
+/*
+cargo run -p ip-api -- lookup 8.8.8.8 1.1.1.1
+
+cargo run -p ip-api -- --format csv batch -i ips.txt
+
+echo '8.8.8.8' | RUST_LOG=debug cargo run -p ip-api -- --format ndjson lookup
+
+IP_API_KEY='YOUR_API_KEY' cargo run -p ip-api -- self
+
+cargo run -p ip-api -- enrich access.log --cache lookups.jsonl > access.enriched.log
+*/
+
+mod enrich;
+mod input;
+mod output;
+
+use std::{
+    error, fmt,
+    io::{self, Write as _},
+    path::PathBuf,
+    process::ExitCode,
+    time::Duration,
+};
+
+use clap::{Args, Parser, Subcommand};
+use http_api_client_endpoint::Endpoint;
+use http_api_reqwest_client::{
+    Client as _, ReqwestClient, http_api_client::ClientRespondEndpointError,
+};
+use ip_api_api::{
+    access_log,
+    client::bulk::{self, BulkError},
+    endpoints::{
+        batch::{Batch, BatchQuery, MAX_QUERY},
+        common::EndpointError,
+        json::{Json, JsonResponseBodyJson},
+    },
+    lookup_cache::LookupCache,
+    objects::rate_limit::RateLimit,
+    types::{
+        api_key::{ApiKey, redact_url, redact_urls},
+        lang::Lang,
+    },
+};
+
+use crate::output::Format;
+
+pub const EXIT_FAIL_RESULT: u8 = 1;
+pub const EXIT_USAGE: u8 = 2;
+pub const EXIT_REQUEST_FAILED: u8 = 3;
+pub const EXIT_KEY_INVALID: u8 = 4;
+pub const EXIT_OUTPUT_FAILED: u8 = 5;
+
+/// Look up IP geolocation with ip-api.com
+#[derive(Parser, Debug)]
+#[command(name = "ip-api", version)]
+struct Cli {
+    /// Pro API key, defaults to the IP_API_KEY environment variable
+    #[arg(long, global = true)]
+    key: Option<String>,
+    /// Read the pro API key from a file
+    #[arg(long, global = true, conflicts_with = "key")]
+    key_file: Option<PathBuf>,
+    /// Comma separated field names or a numeric mask
+    #[arg(long, global = true)]
+    fields: Option<String>,
+    /// Response language, e.g. en, de or zh-CN
+    #[arg(long, global = true, value_parser = parse_lang)]
+    lang: Option<Lang>,
+    #[arg(long, short, global = true, value_enum, default_value_t = Format::Table)]
+    format: Format,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// One request per query
+    Lookup(QueryArgs),
+    /// Up to 100 queries per request
+    Batch(QueryArgs),
+    /// Look up the address of this machine
+    #[command(name = "self")]
+    Myself,
+    /// Annotate access logs with the country code and ASN of each client
+    Enrich(EnrichArgs),
+}
+
+#[derive(Args, Debug)]
+struct QueryArgs {
+    /// IP addresses or domains, stdin is read when none are given
+    queries: Vec<String>,
+    /// Read queries from files, `-` for stdin
+    #[arg(long, short)]
+    input: Vec<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct EnrichArgs {
+    /// Common or combined format logs, `-` for stdin, stdin is read when none are given
+    logs: Vec<PathBuf>,
+    /// Keep lookup results in this JSON lines file, interrupted runs resume from it
+    #[arg(long)]
+    cache: Option<PathBuf>,
+}
+
+fn parse_lang(s: &str) -> Result<Lang, String> {
+    s.parse().map_err(|_| format!("unknown lang [{}]", s))
+}
+
+//
+#[derive(Debug)]
+enum CliError {
+    Input(io::Error),
+    NoQueries,
+    /// Message with the key redacted, reqwest errors carry the url.
+    Request(Box<str>),
+    KeyInvalid(Box<str>),
+    Cache(io::Error),
+    /// Writing stdout, e.g. a broken pipe.
+    Output(io::Error),
+}
+
+impl CliError {
+    fn exit_code(&self) -> u8 {
+        match self {
+            Self::Input(_) | Self::NoQueries => EXIT_USAGE,
+            Self::Request(_) => EXIT_REQUEST_FAILED,
+            Self::KeyInvalid(_) => EXIT_KEY_INVALID,
+            Self::Output(_) | Self::Cache(_) => EXIT_OUTPUT_FAILED,
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Input(err) => write!(f, "read input failed: {}", err),
+            Self::NoQueries => write!(f, "no queries"),
+            Self::Request(err) => write!(f, "request failed: {}", err),
+            Self::KeyInvalid(msg) => write!(f, "key invalid: {}", msg),
+            Self::Cache(err) => write!(f, "cache failed: {}", err),
+            Self::Output(err) => write!(f, "write output failed: {}", err),
+        }
+    }
+}
+
+impl error::Error for CliError {}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    pretty_env_logger::init();
+
+    let cli = Cli::parse();
+    match run(cli).await {
+        Ok(code) => ExitCode::from(code),
+        Err(err) => {
+            // The reader went away, e.g. `| head`, nobody to tell.
+            if !matches!(&err, CliError::Output(x) if x.kind() == io::ErrorKind::BrokenPipe) {
+                eprintln!("ip-api: {}", err);
+            }
+            ExitCode::from(err.exit_code())
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<u8, CliError> {
+    let key = match (&cli.key, &cli.key_file) {
+        (Some(key), _) => Some(ApiKey::new(key)),
+        (None, Some(path)) => Some(ApiKey::from_file(path).map_err(CliError::Input)?),
+        (None, None) => ApiKey::from_env(),
+    };
+    let client = ReqwestClient::new().map_err(|err| CliError::Request(err.to_string().into()))?;
+
+    let results = match &cli.command {
+        Command::Lookup(args) => {
+            let queries = read_queries(args)?;
+            let mut results = vec![];
+            for (i, query) in queries.iter().enumerate() {
+                let mut json = Json::new(query, key.clone());
+                json.fields = cli.fields.as_deref().map(Into::into);
+                json.lang = cli.lang;
+                let (result, rate_limit) = respond(&client, &json).await?;
+                results.push(result);
+                if i + 1 < queries.len() {
+                    wait(rate_limit).await;
+                }
+            }
+            results
+        }
+        Command::Batch(args) => {
+            let queries = read_queries(args)?;
+            let mut results = vec![];
+            let mut chunks = queries.chunks(MAX_QUERY).peekable();
+            while let Some(chunk) = chunks.next() {
+                let mut batch =
+                    Batch::new(chunk.iter().map(BatchQuery::new).collect(), key.clone());
+                batch.fields = cli.fields.as_deref().map(Into::into);
+                batch.lang = cli.lang;
+                let (body, rate_limit) = respond(&client, &batch).await?;
+                results.extend(body.0);
+                if chunks.peek().is_some() {
+                    wait(rate_limit).await;
+                }
+            }
+            results
+        }
+        Command::Myself => {
+            let mut json = Json::new("", key.clone());
+            json.fields = cli.fields.as_deref().map(Into::into);
+            json.lang = cli.lang;
+            vec![respond(&client, &json).await?.0]
+        }
+        Command::Enrich(args) => return enrich(&cli, args, key, &client).await,
+    };
+
+    let columns = output::columns(cli.fields.as_deref());
+    let records = output::records(&results, &columns);
+    let header = output::header(&columns, &records);
+    let mut stdout = io::stdout().lock();
+    output::write(cli.format, &header, &records, &mut stdout)
+        .and_then(|_| stdout.flush())
+        .map_err(CliError::Output)?;
+
+    Ok(if results.iter().all(JsonResponseBodyJson::is_success) {
+        0
+    } else {
+        EXIT_FAIL_RESULT
+    })
+}
+
+/// Fail results, e.g. private ranges, are common in logs and do not change the exit code.
+async fn enrich(
+    cli: &Cli,
+    args: &EnrichArgs,
+    key: Option<ApiKey>,
+    client: &ReqwestClient,
+) -> Result<u8, CliError> {
+    let lines = input::read_raw_lines(&args.logs, io::stdin().lock()).map_err(CliError::Input)?;
+    let mut cache = match &args.cache {
+        Some(path) => LookupCache::open(path).map_err(CliError::Cache)?,
+        None => LookupCache::new(),
+    };
+
+    let mut template = Batch::new(vec![], key.clone()).fields(enrich::FIELDS);
+    template.lang = cli.lang;
+    let ips = access_log::remote_addrs(lines.iter().map(String::as_str));
+    let n = bulk::lookup_all(client, &template, &ips, &mut cache, sleep)
+        .await
+        .map_err(|err| match err {
+            BulkError::Respond(err) => respond_error(err),
+            BulkError::Cache(err) => CliError::Cache(err),
+        })?;
+    log::debug!("{} clients, {} looked up", ips.len(), n);
+
+    let mut stdout = io::stdout().lock();
+    enrich::write(cli.format, &lines, &cache, &mut stdout)
+        .and_then(|_| stdout.flush())
+        .map_err(CliError::Output)?;
+    Ok(0)
+}
+
+fn sleep(dur: Duration) -> tokio::time::Sleep {
+    log::info!("rate limited, waiting {:?}", dur);
+    tokio::time::sleep(dur)
+}
+
+/// Free endpoint only, the pro one has no rate limit headers.
+async fn wait(rate_limit: Option<RateLimit>) {
+    if let Some(dur) = rate_limit.as_ref().and_then(bulk::rate_limit_wait) {
+        sleep(dur).await;
+    }
+}
+
+fn read_queries(args: &QueryArgs) -> Result<Vec<String>, CliError> {
+    let queries = input::read_queries(&args.queries, &args.input, io::stdin().lock())
+        .map_err(CliError::Input)?;
+    if queries.is_empty() {
+        return Err(CliError::NoQueries);
+    }
+    Ok(queries)
+}
+
+async fn respond<EP, T>(
+    client: &ReqwestClient,
+    endpoint: &EP,
+) -> Result<(T, Option<RateLimit>), CliError>
+where
+    EP: Endpoint<
+            RenderRequestError = EndpointError,
+            ParseResponseOutput = (T, Option<RateLimit>),
+            ParseResponseError = EndpointError,
+        > + Send
+        + Sync,
+{
+    let (body, rate_limit) = client
+        .respond_endpoint_with_callback(
+            endpoint,
+            |req| {
+                log::debug!("{} {}", req.method(), redact_url(req.uri().to_string()));
+                req
+            },
+            |_| {},
+        )
+        .await
+        .map_err(respond_error)?;
+    log::debug!("{:?}", rate_limit);
+    Ok((body, rate_limit))
+}
+
+fn respond_error<RE>(err: ClientRespondEndpointError<RE, EndpointError, EndpointError>) -> CliError
+where
+    RE: error::Error + Send + Sync + 'static,
+{
+    match err {
+        ClientRespondEndpointError::EndpointParseResponseFailed(EndpointError::KeyInvalid(msg)) => {
+            CliError::KeyInvalid(msg)
+        }
+        // Request urls carry the key.
+        err => CliError::Request(redact_urls(err.to_string()).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use clap::CommandFactory as _;
+
+    #[test]
+    fn test_cli() {
+        Cli::command().debug_assert();
+
+        let cli = Cli::try_parse_from([
+            "ip-api", "batch", "8.8.8.8", "-i", "-", "--format", "csv", "--lang", "zh-CN",
+        ])
+        .unwrap();
+        assert_eq!(cli.format, Format::Csv);
+        assert!(matches!(cli.lang, Some(Lang::ZH_CN)));
+        let Command::Batch(args) = cli.command else {
+            panic!()
+        };
+        assert_eq!(args.queries, ["8.8.8.8"]);
+        assert_eq!(args.input, [PathBuf::from("-")]);
+
+        let cli = Cli::try_parse_from(["ip-api", "self", "--fields", "query"]).unwrap();
+        assert!(matches!(cli.command, Command::Myself));
+        assert_eq!(cli.fields.as_deref(), Some("query"));
+
+        let err = Cli::try_parse_from(["ip-api", "lookup", "--lang", "xx"]).unwrap_err();
+        assert_eq!(err.exit_code(), EXIT_USAGE as i32);
+        assert!(Cli::try_parse_from(["ip-api", "self", "--key", "a", "--key-file", "b"]).is_err());
+
+        let cli =
+            Cli::try_parse_from(["ip-api", "enrich", "a.log", "-", "--cache", "c.jsonl"]).unwrap();
+        let Command::Enrich(args) = cli.command else {
+            panic!()
+        };
+        assert_eq!(args.logs, [PathBuf::from("a.log"), PathBuf::from("-")]);
+        assert_eq!(args.cache, Some(PathBuf::from("c.jsonl")));
+    }
+
+    #[test]
+    fn test_exit_code() {
+        let err = CliError::Output(io::ErrorKind::BrokenPipe.into());
+        assert_eq!(err.exit_code(), EXIT_OUTPUT_FAILED);
+        assert_ne!(
+            CliError::Request("timed out".into()).exit_code(),
+            EXIT_OUTPUT_FAILED
+        );
+        // The cache is written as lookups complete, e.g. a full disk.
+        let err = CliError::Cache(io::ErrorKind::StorageFull.into());
+        assert_eq!(err.exit_code(), EXIT_OUTPUT_FAILED);
+    }
+
+    #[test]
+    fn test_respond_error_redacts_key() {
+        let err = respond_error(
+            ClientRespondEndpointError::<io::Error, _, _>::RespondFailed(io::Error::other(
+                "https://pro.ip-api.com/json/24.48.0.1?key=f%2Bo%26o&lang=en",
+            )),
+        );
+        let CliError::Request(msg) = err else {
+            panic!("{:?}", err)
+        };
+        assert!(msg.contains("?key=***&lang=en"), "{}", msg);
+        assert!(!msg.contains("f%2Bo"), "{}", msg);
+    }
+}
@@ -0,0 +1,227 @@
+//! Render results as table, JSON, NDJSON or CSV.
+//!
+//! Every format shows the same columns, the requested `--fields` names in order
+//! (numeric masks in ip-api's field order), or ip-api's default fields.
+
+use std::io::{self, Write};
+
+use clap::ValueEnum;
+use ip_api_api::endpoints::{
+    fields::{DEFAULT_FIELDS, field_names},
+    json::JsonResponseBodyJson,
+};
+use serde::{Serialize, Serializer, ser::SerializeMap as _};
+use serde_json::Value;
+
+//
+#[derive(ValueEnum, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    Table,
+    Json,
+    Ndjson,
+    Csv,
+}
+
+/// Ordered, unlike `serde_json::Map`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record(pub Vec<(String, Value)>);
+
+impl Record {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.0.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+}
+
+impl Serialize for Record {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (k, v) in &self.0 {
+            map.serialize_entry(k, v)?;
+        }
+        map.end()
+    }
+}
+
+/// Column names from `--fields`.
+pub fn columns(fields: Option<&str>) -> Vec<String> {
+    match fields {
+        Some(fields) => field_names(fields).into_iter().map(Into::into).collect(),
+        None => DEFAULT_FIELDS.iter().map(|x| x.to_string()).collect(),
+    }
+}
+
+pub fn records(results: &[JsonResponseBodyJson], columns: &[String]) -> Vec<Record> {
+    results
+        .iter()
+        .map(|result| {
+            let Ok(Value::Object(map)) = serde_json::to_value(result) else {
+                unreachable!("results serialize to objects")
+            };
+            Record(
+                columns
+                    .iter()
+                    .filter_map(|k| map.get(k).map(|v| (k.to_owned(), v.to_owned())))
+                    .collect(),
+            )
+        })
+        .collect()
+}
+
+/// `header` only applies to table and CSV.
+pub fn write(
+    format: Format,
+    header: &[String],
+    records: &[Record],
+    mut w: impl Write,
+) -> io::Result<()> {
+    match format {
+        Format::Table => write_table(header, records, &mut w),
+        Format::Json => {
+            match records {
+                [record] => serde_json::to_writer_pretty(&mut w, record)?,
+                _ => serde_json::to_writer_pretty(&mut w, records)?,
+            }
+            writeln!(w)
+        }
+        Format::Ndjson => {
+            for record in records {
+                serde_json::to_writer(&mut w, record)?;
+                writeln!(w)?;
+            }
+            Ok(())
+        }
+        Format::Csv => {
+            let mut writer = csv::Writer::from_writer(w);
+            writer.write_record(header)?;
+            for record in records {
+                writer.write_record(header.iter().map(|k| cell(record.get(k))))?;
+            }
+            writer.flush()
+        }
+    }
+}
+
+/// `columns` present in any record.
+pub fn header(columns: &[String], records: &[Record]) -> Vec<String> {
+    columns
+        .iter()
+        .filter(|k| records.iter().any(|x| x.get(k).is_some()))
+        .cloned()
+        .collect()
+}
+
+fn cell(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.to_owned(),
+        Some(v) => v.to_string(),
+    }
+}
+
+fn write_table(header: &[String], records: &[Record], w: &mut impl Write) -> io::Result<()> {
+    let rows = records
+        .iter()
+        .map(|record| {
+            header
+                .iter()
+                .map(|k| cell(record.get(k)))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let widths = header
+        .iter()
+        .enumerate()
+        .map(|(i, k)| {
+            rows.iter()
+                .map(|row| row[i].chars().count())
+                .chain([k.chars().count()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+
+    for row in [header.to_vec()].iter().chain(rows.iter()) {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .map(|(s, width)| format!("{:<width$}", s, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+        writeln!(w, "{}", line.trim_end())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn results() -> Vec<JsonResponseBodyJson> {
+        vec![
+            serde_json::from_str(
+                r#"{"status":"success","country":"Canada","countryCode":"CA","lat":45.6085,"query":"24.48.0.1"}"#,
+            )
+            .unwrap(),
+            serde_json::from_str(
+                r#"{"status":"fail","message":"private range","query":"10.0.0.1"}"#,
+            )
+            .unwrap(),
+        ]
+    }
+
+    fn render(format: Format, fields: Option<&str>) -> String {
+        let columns = columns(fields);
+        let records = records(&results(), &columns);
+        let header = header(&columns, &records);
+        let mut buf = vec![];
+        write(format, &header, &records, &mut buf).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn test_columns() {
+        assert_eq!(columns(Some("status, query,")), ["status", "query"]);
+        assert_eq!(columns(Some("24578")), ["status", "countryCode", "query"]);
+        assert_eq!(columns(None).len(), DEFAULT_FIELDS.len());
+    }
+
+    #[test]
+    fn test_write() {
+        let fields = Some("status,message,countryCode,lat,query");
+
+        assert_eq!(
+            render(Format::Table, fields),
+            "\
+status   message        countryCode  lat      query
+success                 CA           45.6085  24.48.0.1
+fail     private range                        10.0.0.1
+"
+        );
+        assert_eq!(
+            render(Format::Csv, fields),
+            "\
+status,message,countryCode,lat,query
+success,,CA,45.6085,24.48.0.1
+fail,private range,,,10.0.0.1
+"
+        );
+        assert_eq!(
+            render(Format::Ndjson, fields),
+            r#"{"status":"success","countryCode":"CA","lat":45.6085,"query":"24.48.0.1"}
+{"status":"fail","message":"private range","query":"10.0.0.1"}
+"#
+        );
+        assert!(render(Format::Json, fields).starts_with("[\n  {\n    \"status\": \"success\""));
+
+        // Numeric masks keep everything returned, nothing is made up for the rest.
+        let ndjson = render(Format::Ndjson, Some("66846719"));
+        assert!(ndjson.starts_with(r#"{"status":"success","country":"Canada""#));
+        assert!(!ndjson.contains("district"));
+        // Defaults do not.
+        assert!(!render(Format::Ndjson, None).contains("district"));
+    }
+}
@@ -0,0 +1,54 @@
+#!/usr/bin/env python3
+"""Generate ip-api-api/src/types/subdivision/codes.rs from iso-codes `iso_3166-2.json`.
+
+Source: iso-codes 4.15.0, https://salsa.debian.org/iso-codes-team/iso-codes, `data/iso_3166-2.json`,
+also installed by the Debian/Ubuntu `iso-codes` package as /usr/share/iso-codes/json/iso_3166-2.json.
+
+    scripts/gen_subdivision_codes.py [ISO_3166_2_JSON]          # regenerate
+    scripts/gen_subdivision_codes.py --check [ISO_3166_2_JSON]  # fail if out of date
+"""
+
+import hashlib
+import json
+import pathlib
+import sys
+
+SOURCE_DEFAULT = "/usr/share/iso-codes/json/iso_3166-2.json"
+VERSION = "4.15"
+OUTPUT = pathlib.Path(__file__).resolve().parent.parent / "ip-api-api/src/types/subdivision/codes.rs"
+PER_LINE = 10
+
+
+def render(source: bytes) -> str:
+    codes = sorted({x["code"] for x in json.loads(source)["3166-2"]})
+    lines = [
+        "    " + " ".join(f'"{code}",' for code in codes[i : i + PER_LINE])
+        for i in range(0, len(codes), PER_LINE)
+    ]
+    return (
+        f"//! Generated from iso-codes {VERSION} `iso_3166-2.json`, sorted for binary search.\n"
+        f"//! sha256 {hashlib.sha256(source).hexdigest()}, regenerate with\n"
+        "//! `scripts/gen_subdivision_codes.py`.\n"
+        "\n"
+        "#[rustfmt::skip]\n"
+        "pub(super) static CODES: &[&str] = &[\n" + "\n".join(lines) + "\n];\n"
+    )
+
+
+def main(args: list[str]) -> int:
+    check = "--check" in args
+    args = [x for x in args if x != "--check"]
+    source = pathlib.Path(args[0] if args else SOURCE_DEFAULT).read_bytes()
+    rendered = render(source)
+
+    if check:
+        if OUTPUT.read_text() != rendered:
+            print(f"{OUTPUT} is out of date", file=sys.stderr)
+            return 1
+        return 0
+    OUTPUT.write_text(rendered)
+    return 0
+
+
+if __name__ == "__main__":
+    sys.exit(main(sys.argv[1:]))